@@ -0,0 +1,76 @@
+use nalgebra_glm::Vec3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn vacia() -> Self {
+        Aabb {
+            min: Vec3::repeat(f32::INFINITY),
+            max: Vec3::repeat(f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, otra: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&otra.min),
+            max: self.max.sup(&otra.max),
+        }
+    }
+
+    pub fn incluir_punto(&self, punto: &Vec3) -> Aabb {
+        Aabb {
+            min: self.min.inf(punto),
+            max: self.max.sup(punto),
+        }
+    }
+
+    pub fn centro(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn area_superficie(&self) -> f32 {
+        let d = self.max - self.min;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn eje_mayor(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x >= d.y && d.x >= d.z {
+            0
+        } else if d.y >= d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    // Prueba de planos (slabs); devuelve la distancia de entrada si el rayo toca la caja antes de t_max.
+    pub fn interseca(&self, ray_origin: &Vec3, inv_dir: &Vec3, t_max: f32) -> Option<f32> {
+        let mut entrada = 0.0f32;
+        let mut salida = t_max;
+
+        for i in 0..3 {
+            let t0 = (self.min[i] - ray_origin[i]) * inv_dir[i];
+            let t1 = (self.max[i] - ray_origin[i]) * inv_dir[i];
+            entrada = entrada.max(t0.min(t1));
+            salida = salida.min(t0.max(t1));
+        }
+
+        if entrada <= salida {
+            Some(entrada)
+        } else {
+            None
+        }
+    }
+}
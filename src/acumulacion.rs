@@ -0,0 +1,52 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+
+// Suma la radiancia lineal de los frames mientras la escena no cambia y deja el promedio, ya pasado a
+// sRGB de 8 bits, en el framebuffer. Se reinicia cuando la camara se mueve o la animacion cambia algo,
+// asi que una escena con sol u oleaje solo converge con la animacion en pausa.
+pub struct Acumulador {
+    suma: Vec<[f32; 3]>,
+    muestras: u32,
+}
+
+impl Acumulador {
+    pub fn new(width: usize, height: usize) -> Self {
+        Acumulador {
+            suma: vec![[0.0; 3]; width * height],
+            muestras: 0,
+        }
+    }
+
+    pub fn muestras(&self) -> u32 {
+        self.muestras
+    }
+
+    // Radiancia lineal promedio de cada pixel, sin cuantizar.
+    pub fn promedio(&self) -> Vec<[f32; 3]> {
+        let peso = 1.0 / self.muestras.max(1) as f32;
+        self.suma.iter().map(|suma| [suma[0] * peso, suma[1] * peso, suma[2] * peso]).collect()
+    }
+
+    pub fn reiniciar(&mut self) {
+        for pixel in self.suma.iter_mut() {
+            *pixel = [0.0; 3];
+        }
+        self.muestras = 0;
+    }
+
+    pub fn acumular(&mut self, frame: &[[f32; 3]], framebuffer: &mut Framebuffer) {
+        self.muestras += 1;
+        let peso = 1.0 / self.muestras as f32;
+
+        let width = framebuffer.width;
+        for (i, (suma, radiancia)) in self.suma.iter_mut().zip(frame).enumerate() {
+            for canal in 0..3 {
+                suma[canal] += radiancia[canal];
+            }
+
+            let promedio = [suma[0] * peso, suma[1] * peso, suma[2] * peso];
+            framebuffer.set_current_color(Color::desde_lineal(&promedio).to_hex());
+            framebuffer.point(i % width, i / width);
+        }
+    }
+}
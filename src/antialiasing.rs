@@ -0,0 +1,103 @@
+use std::f32::consts::PI;
+use std::str::FromStr;
+use crate::random::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Muestreo {
+    Estratificado,
+    Aleatorio,
+}
+
+// Los filtros se aplican muestreando su forma: cada muestra pesa lo mismo y el promedio queda filtrado,
+// asi tambien es correcto promediar frames en el acumulador.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filtro {
+    Caja,
+    Tienda,
+    Gauss,
+}
+
+const SIGMA_GAUSS: f32 = 0.5;
+
+impl Filtro {
+    pub fn siguiente(self) -> Self {
+        match self {
+            Filtro::Caja => Filtro::Tienda,
+            Filtro::Tienda => Filtro::Gauss,
+            Filtro::Gauss => Filtro::Caja,
+        }
+    }
+
+    // Convierte un punto uniforme de [0, 1)^2 en un desplazamiento desde el centro del pixel.
+    fn desplazamiento(self, u: f32, v: f32) -> (f32, f32) {
+        match self {
+            Filtro::Caja => (u - 0.5, v - 0.5),
+            Filtro::Tienda => (tienda(u), tienda(v)),
+            Filtro::Gauss => {
+                let r = SIGMA_GAUSS * (-2.0 * (1.0 - u).ln()).sqrt();
+                let theta = 2.0 * PI * v;
+                (r * theta.cos(), r * theta.sin())
+            }
+        }
+    }
+}
+
+fn tienda(u: f32) -> f32 {
+    let u = 2.0 * u;
+    if u < 1.0 {
+        u.sqrt() - 1.0
+    } else {
+        1.0 - (2.0 - u).sqrt()
+    }
+}
+
+impl Muestreo {
+    pub fn siguiente(self) -> Self {
+        match self {
+            Muestreo::Estratificado => Muestreo::Aleatorio,
+            Muestreo::Aleatorio => Muestreo::Estratificado,
+        }
+    }
+}
+
+impl FromStr for Filtro {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<Self, String> {
+        match texto {
+            "box" => Ok(Filtro::Caja),
+            "tent" => Ok(Filtro::Tienda),
+            "gauss" => Ok(Filtro::Gauss),
+            otro => Err(format!("filtro desconocido: {} (box, tent o gauss)", otro)),
+        }
+    }
+}
+
+impl FromStr for Muestreo {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<Self, String> {
+        match texto {
+            "stratified" => Ok(Muestreo::Estratificado),
+            "random" => Ok(Muestreo::Aleatorio),
+            otro => Err(format!("muestreo desconocido: {} (stratified o random)", otro)),
+        }
+    }
+}
+
+// Desplazamientos de las n x n muestras de un pixel.
+pub fn desplazamientos(n: u32, muestreo: Muestreo, filtro: Filtro, rng: &mut Rng) -> Vec<(f32, f32)> {
+    let n = n.max(1);
+    (0..n * n)
+        .map(|i| {
+            let (u, v) = match muestreo {
+                Muestreo::Estratificado => (
+                    ((i % n) as f32 + rng.next_f32()) / n as f32,
+                    ((i / n) as f32 + rng.next_f32()) / n as f32,
+                ),
+                Muestreo::Aleatorio => (rng.next_f32(), rng.next_f32()),
+            };
+            filtro.desplazamiento(u, v)
+        })
+        .collect()
+}
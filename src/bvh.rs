@@ -0,0 +1,382 @@
+use nalgebra_glm::Vec3;
+use crate::aabb::Aabb;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+const CUBETAS_SAH: usize = 12;
+const MAX_OBJETOS_HOJA: usize = 2;
+const COSTO_RECORRIDO: f32 = 0.5;
+
+enum Nodo {
+    Hoja { caja: Aabb, inicio: usize, cantidad: usize },
+    Interno { caja: Aabb, izquierdo: usize, derecho: usize },
+}
+
+impl Nodo {
+    fn caja(&self) -> &Aabb {
+        match self {
+            Nodo::Hoja { caja, .. } | Nodo::Interno { caja, .. } => caja,
+        }
+    }
+}
+
+// Un arbol sobre un subconjunto de los objetos; los indices apuntan a la lista del Bvh.
+struct Arbol {
+    indices: Vec<usize>,
+    nodos: Vec<Nodo>,
+}
+
+impl Arbol {
+    fn new(cajas: &[Aabb], indices: Vec<usize>) -> Self {
+        let centros: Vec<Vec3> = cajas.iter().map(|caja| caja.centro()).collect();
+        let cantidad = indices.len();
+        let mut arbol = Arbol { indices, nodos: Vec::new() };
+
+        if cantidad > 0 {
+            arbol.construir(cajas, &centros, 0, cantidad);
+        }
+        arbol
+    }
+
+    fn construir(&mut self, cajas: &[Aabb], centros: &[Vec3], inicio: usize, fin: usize) -> usize {
+        let caja = self.indices[inicio..fin].iter().fold(Aabb::vacia(), |acc, &i| acc.union(&cajas[i]));
+        let cantidad = fin - inicio;
+        let nodo = self.nodos.len();
+
+        if cantidad <= MAX_OBJETOS_HOJA {
+            self.nodos.push(Nodo::Hoja { caja, inicio, cantidad });
+            return nodo;
+        }
+
+        let caja_centros = self.indices[inicio..fin].iter().fold(Aabb::vacia(), |acc, &i| acc.incluir_punto(&centros[i]));
+        let eje = caja_centros.eje_mayor();
+        let extension = caja_centros.max[eje] - caja_centros.min[eje];
+
+        if extension <= f32::EPSILON {
+            self.nodos.push(Nodo::Hoja { caja, inicio, cantidad });
+            return nodo;
+        }
+
+        let cubeta_de = |i: usize| {
+            let relativo = (centros[i][eje] - caja_centros.min[eje]) / extension;
+            ((relativo * CUBETAS_SAH as f32) as usize).min(CUBETAS_SAH - 1)
+        };
+
+        let mut conteos = [0usize; CUBETAS_SAH];
+        let mut cajas_cubeta = [Aabb::vacia(); CUBETAS_SAH];
+        for &i in &self.indices[inicio..fin] {
+            let cubeta = cubeta_de(i);
+            conteos[cubeta] += 1;
+            cajas_cubeta[cubeta] = cajas_cubeta[cubeta].union(&cajas[i]);
+        }
+
+        // Costo SAH de cortar despues de cada cubeta, barriendo de izquierda a derecha y al reves.
+        let mut costos = [0.0f32; CUBETAS_SAH - 1];
+        let mut acumulada = Aabb::vacia();
+        let mut conteo = 0;
+        for corte in 0..CUBETAS_SAH - 1 {
+            acumulada = acumulada.union(&cajas_cubeta[corte]);
+            conteo += conteos[corte];
+            costos[corte] = conteo as f32 * acumulada.area_superficie();
+        }
+        let mut acumulada = Aabb::vacia();
+        let mut conteo = 0;
+        for corte in (0..CUBETAS_SAH - 1).rev() {
+            acumulada = acumulada.union(&cajas_cubeta[corte + 1]);
+            conteo += conteos[corte + 1];
+            costos[corte] += conteo as f32 * acumulada.area_superficie();
+        }
+
+        let (mejor_corte, mejor_costo) = costos
+            .iter()
+            .enumerate()
+            .fold((0, f32::INFINITY), |mejor, (corte, &costo)| if costo < mejor.1 { (corte, costo) } else { mejor });
+
+        let costo_hoja = cantidad as f32;
+        let costo_division = COSTO_RECORRIDO + mejor_costo / caja.area_superficie().max(f32::EPSILON);
+        if costo_division >= costo_hoja {
+            self.nodos.push(Nodo::Hoja { caja, inicio, cantidad });
+            return nodo;
+        }
+
+        let mut medio = inicio;
+        for k in inicio..fin {
+            if cubeta_de(self.indices[k]) <= mejor_corte {
+                self.indices.swap(k, medio);
+                medio += 1;
+            }
+        }
+
+        if medio == inicio || medio == fin {
+            medio = inicio + cantidad / 2;
+        }
+
+        self.nodos.push(Nodo::Hoja { caja, inicio, cantidad });
+        let izquierdo = self.construir(cajas, centros, inicio, medio);
+        let derecho = self.construir(cajas, centros, medio, fin);
+        self.nodos[nodo] = Nodo::Interno { caja, izquierdo, derecho };
+
+        nodo
+    }
+
+    fn intersect(&self, objects: &[Box<dyn RayIntersect>], ray_origin: &Vec3, ray_direction: &Vec3, intersect: &mut Intersect) {
+        if self.nodos.is_empty() {
+            return;
+        }
+
+        let inv_dir = ray_direction.map(|c| 1.0 / c);
+        let mut zbuffer = if intersect.is_intersecting { intersect.distance } else { f32::INFINITY };
+        let mut pila = vec![0];
+
+        while let Some(nodo) = pila.pop() {
+            if self.nodos[nodo].caja().interseca(ray_origin, &inv_dir, zbuffer).is_none() {
+                continue;
+            }
+
+            match self.nodos[nodo] {
+                Nodo::Hoja { inicio, cantidad, .. } => {
+                    for &i in &self.indices[inicio..inicio + cantidad] {
+                        let tmp = objects[i].ray_intersect(ray_origin, ray_direction);
+                        if tmp.is_intersecting && tmp.distance < zbuffer {
+                            zbuffer = tmp.distance;
+                            *intersect = tmp;
+                        }
+                    }
+                }
+                Nodo::Interno { izquierdo, derecho, .. } => {
+                    let t_izq = self.nodos[izquierdo].caja().interseca(ray_origin, &inv_dir, zbuffer);
+                    let t_der = self.nodos[derecho].caja().interseca(ray_origin, &inv_dir, zbuffer);
+
+                    // Se apila primero el hijo lejano para visitar antes el cercano.
+                    match (t_izq, t_der) {
+                        (Some(a), Some(b)) if a <= b => pila.extend([derecho, izquierdo]),
+                        (Some(_), Some(_)) => pila.extend([izquierdo, derecho]),
+                        (Some(_), None) => pila.push(izquierdo),
+                        (None, Some(_)) => pila.push(derecho),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn ocluido(&self, objects: &[Box<dyn RayIntersect>], ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32) -> bool {
+        if self.nodos.is_empty() {
+            return false;
+        }
+
+        let inv_dir = ray_direction.map(|c| 1.0 / c);
+        let mut pila = vec![0];
+
+        while let Some(nodo) = pila.pop() {
+            if self.nodos[nodo].caja().interseca(ray_origin, &inv_dir, max_distance).is_none() {
+                continue;
+            }
+
+            match self.nodos[nodo] {
+                Nodo::Hoja { inicio, cantidad, .. } => {
+                    for &i in &self.indices[inicio..inicio + cantidad] {
+                        let tmp = objects[i].ray_intersect(ray_origin, ray_direction);
+                        if tmp.is_intersecting && tmp.distance < max_distance {
+                            return true;
+                        }
+                    }
+                }
+                Nodo::Interno { izquierdo, derecho, .. } => pila.extend([izquierdo, derecho]),
+            }
+        }
+
+        false
+    }
+}
+
+// Los objetos animados van en un arbol aparte que se reconstruye en cada frame; el de los estaticos,
+// que son casi todos, se construye una sola vez.
+pub struct Bvh {
+    objects: Vec<Box<dyn RayIntersect>>,
+    dinamico: Vec<bool>,
+    estaticos: Arbol,
+    dinamicos: Arbol,
+    transparentes: bool,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Box<dyn RayIntersect>>) -> Self {
+        let mut bvh = Bvh {
+            dinamico: vec![false; objects.len()],
+            transparentes: objects.iter().any(|object| object.transparente()),
+            objects,
+            estaticos: Arbol { indices: Vec::new(), nodos: Vec::new() },
+            dinamicos: Arbol { indices: Vec::new(), nodos: Vec::new() },
+        };
+        bvh.marcar_dinamicos(&[]);
+        bvh
+    }
+
+    pub fn hay_transparentes(&self) -> bool {
+        self.transparentes
+    }
+
+    // Separa los objetos que se van a mover con objects_mut; reconstruye los dos arboles.
+    pub fn marcar_dinamicos(&mut self, indices: &[usize]) {
+        self.dinamico = vec![false; self.objects.len()];
+        for &i in indices {
+            self.dinamico[i] = true;
+        }
+
+        let cajas: Vec<Aabb> = self.objects.iter().map(|object| object.bounding_box()).collect();
+        let estaticos = (0..self.objects.len()).filter(|&i| !self.dinamico[i]).collect();
+        self.estaticos = Arbol::new(&cajas, estaticos);
+        self.reconstruir_dinamicos();
+    }
+
+    pub fn objects_mut(&mut self) -> &mut [Box<dyn RayIntersect>] {
+        &mut self.objects
+    }
+
+    // Se llama despues de mover objetos dinamicos con objects_mut para que las cajas vuelvan a cubrirlos.
+    pub fn reconstruir_dinamicos(&mut self) {
+        let cajas: Vec<Aabb> = self.objects
+            .iter()
+            .zip(&self.dinamico)
+            .map(|(object, &dinamico)| if dinamico { object.bounding_box() } else { Aabb::vacia() })
+            .collect();
+        let dinamicos = (0..self.objects.len()).filter(|&i| self.dinamico[i]).collect();
+        self.dinamicos = Arbol::new(&cajas, dinamicos);
+    }
+
+    pub fn intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let mut intersect = Intersect::empty();
+        self.estaticos.intersect(&self.objects, ray_origin, ray_direction, &mut intersect);
+        self.dinamicos.intersect(&self.objects, ray_origin, ray_direction, &mut intersect);
+        intersect
+    }
+
+    pub fn ocluido(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32) -> bool {
+        self.estaticos.ocluido(&self.objects, ray_origin, ray_direction, max_distance)
+            || self.dinamicos.ocluido(&self.objects, ray_origin, ray_direction, max_distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::material::Material;
+    use crate::random::Rng;
+    use crate::sphere::Sphere;
+
+    fn punto(rng: &mut Rng, extension: f32) -> Vec3 {
+        Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()).map(|c| (c * 2.0 - 1.0) * extension)
+    }
+
+    // Misma semilla, mismos objetos: uno para el BVH y otro para el recorrido lineal.
+    fn objetos(semilla: u64) -> Vec<Box<dyn RayIntersect>> {
+        let mut rng = Rng::new(semilla);
+        let material = Material::new(Color::new(200, 200, 200), 1.0, [1.0, 0.0], 0.0, 0.0, 1.0, None, None);
+
+        (0..300)
+            .map(|i| -> Box<dyn RayIntersect> {
+                let centro = punto(&mut rng, 10.0);
+                let tamano = 0.2 + rng.next_f32() * 1.8;
+                if i % 3 == 0 {
+                    Box::new(Sphere { center: centro, radius: tamano / 2.0, material: material.clone() })
+                } else {
+                    Box::new(Cube::new(centro, tamano, std::array::from_fn(|_| material.clone())))
+                }
+            })
+            .collect()
+    }
+
+    fn rayos(semilla: u64) -> Vec<(Vec3, Vec3)> {
+        let mut rng = Rng::new(semilla);
+        (0..2000)
+            .map(|_| (punto(&mut rng, 15.0), punto(&mut rng, 1.0).normalize()))
+            .collect()
+    }
+
+    fn mas_cercano(objects: &[Box<dyn RayIntersect>], origen: &Vec3, direccion: &Vec3) -> Intersect {
+        let mut intersect = Intersect::empty();
+        for object in objects {
+            let tmp = object.ray_intersect(origen, direccion);
+            if tmp.is_intersecting && (!intersect.is_intersecting || tmp.distance < intersect.distance) {
+                intersect = tmp;
+            }
+        }
+        intersect
+    }
+
+    #[test]
+    fn intersect_coincide_con_el_recorrido_lineal() {
+        let bvh = Bvh::new(objetos(7));
+        let lineal = objetos(7);
+
+        for (origen, direccion) in rayos(11) {
+            let esperado = mas_cercano(&lineal, &origen, &direccion);
+            let obtenido = bvh.intersect(&origen, &direccion);
+
+            assert_eq!(obtenido.is_intersecting, esperado.is_intersecting, "rayo {:?} {:?}", origen, direccion);
+            if esperado.is_intersecting {
+                assert!((obtenido.distance - esperado.distance).abs() < 1e-4, "rayo {:?} {:?}", origen, direccion);
+            }
+        }
+    }
+
+    #[test]
+    fn ocluido_coincide_con_el_recorrido_lineal() {
+        let bvh = Bvh::new(objetos(3));
+        let lineal = objetos(3);
+        let mut rng = Rng::new(5);
+
+        for (origen, direccion) in rayos(13) {
+            let distancia = rng.next_f32() * 30.0;
+            let esperado = lineal.iter().any(|object| {
+                let tmp = object.ray_intersect(&origen, &direccion);
+                tmp.is_intersecting && tmp.distance < distancia
+            });
+
+            assert_eq!(bvh.ocluido(&origen, &direccion, distancia), esperado, "rayo {:?} {:?} hasta {}", origen, direccion, distancia);
+        }
+    }
+
+    #[test]
+    fn los_objetos_dinamicos_se_encuentran_despues_de_moverlos() {
+        let mut bvh = Bvh::new(objetos(19));
+        let mut lineal = objetos(19);
+        let dinamicos: Vec<usize> = (0..300).step_by(7).collect();
+        bvh.marcar_dinamicos(&dinamicos);
+
+        let mover = |objects: &mut [Box<dyn RayIntersect>]| {
+            for &i in &dinamicos {
+                if let Some(cubo) = objects[i].as_any_mut().downcast_mut::<Cube>() {
+                    cubo.center += Vec3::new(3.0, -2.0, 1.0);
+                } else if let Some(esfera) = objects[i].as_any_mut().downcast_mut::<Sphere>() {
+                    esfera.center -= Vec3::new(4.0, 0.0, 2.0);
+                }
+            }
+        };
+        mover(bvh.objects_mut());
+        mover(&mut lineal);
+        bvh.reconstruir_dinamicos();
+
+        for (origen, direccion) in rayos(31) {
+            let esperado = mas_cercano(&lineal, &origen, &direccion);
+            let obtenido = bvh.intersect(&origen, &direccion);
+
+            assert_eq!(obtenido.is_intersecting, esperado.is_intersecting, "rayo {:?} {:?}", origen, direccion);
+            if esperado.is_intersecting {
+                assert!((obtenido.distance - esperado.distance).abs() < 1e-4, "rayo {:?} {:?}", origen, direccion);
+                assert!(bvh.ocluido(&origen, &direccion, esperado.distance + 1e-3));
+            }
+        }
+    }
+
+    #[test]
+    fn sin_objetos_no_hay_golpes() {
+        let bvh = Bvh::new(Vec::new());
+        let direccion = Vec3::new(0.0, 0.0, -1.0);
+
+        assert!(!bvh.intersect(&Vec3::zeros(), &direccion).is_intersecting);
+        assert!(!bvh.ocluido(&Vec3::zeros(), &direccion, f32::INFINITY));
+    }
+}
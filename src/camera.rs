@@ -24,7 +24,7 @@ impl Camera {
 
         let rotated = vector.x * right + vector.y * up - vector.z * forward;
 
-        return rotated.normalize();
+        rotated.normalize()
     }
 
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
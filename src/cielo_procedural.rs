@@ -0,0 +1,76 @@
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::pbr::{color_a_vec3, vec3_a_color};
+
+// Degradado entre el horizonte y el cenit que pasa de los colores de noche a los de dia segun la altura
+// del sol, con un resplandor alrededor del sol que crece cuando este se acerca al horizonte.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CieloProcedural {
+    pub cenit: Color,
+    pub horizonte: Color,
+    pub cenit_noche: Color,
+    pub horizonte_noche: Color,
+    pub color_sol: Color,
+    pub tamano_sol: f32,
+    pub direccion_sol: Vec3,
+    // Menor y mayor altura (componente y de la direccion) que alcanza el sol en su recorrido.
+    pub elevacion_sol: [f32; 2],
+}
+
+impl CieloProcedural {
+    // Altura del sol llevada a [-1, 1] segun su recorrido: -1 en el punto mas bajo y 1 en el mas alto, asi
+    // una orbita que nunca baja del horizonte igual pasa por la noche.
+    fn elevacion_relativa(&self) -> f32 {
+        let [minima, maxima] = self.elevacion_sol;
+        if maxima - minima < 1e-4 {
+            return self.direccion_sol.y;
+        }
+        (self.direccion_sol.y - minima) / (maxima - minima) * 2.0 - 1.0
+    }
+
+    // 0 con el sol bien abajo, 1 cuando ya esta alto.
+    fn dia(&self) -> f32 {
+        let t = ((self.elevacion_relativa() + 0.1) / 0.4).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    // Cenit y horizonte a la hora actual.
+    fn degradado(&self) -> (Vec3, Vec3) {
+        let dia = self.dia();
+        let cenit = color_a_vec3(self.cenit_noche).lerp(&color_a_vec3(self.cenit), dia);
+        let horizonte = color_a_vec3(self.horizonte_noche).lerp(&color_a_vec3(self.horizonte), dia);
+        (cenit, horizonte)
+    }
+
+    // Color de arriba para la luz de hemisferio: el promedio entre cenit y horizonte, sin el resplandor del
+    // sol, que ya llega por su propia luz.
+    pub fn color_ambiente(&self) -> Color {
+        let (cenit, horizonte) = self.degradado();
+        vec3_a_color(&((cenit + horizonte) * 0.5))
+    }
+
+    pub fn color(&self, direccion: &Vec3) -> Color {
+        let direccion = direccion.normalize();
+        let (cenit, horizonte) = self.degradado();
+
+        // Debajo del horizonte el cielo se oscurece un poco en vez de mostrar el cenit.
+        let altura = direccion.y;
+        let mut color = if altura >= 0.0 {
+            horizonte.lerp(&cenit, altura.sqrt())
+        } else {
+            horizonte * (1.0 + altura * 0.5)
+        };
+
+        let sol = color_a_vec3(self.color_sol);
+        let coseno = direccion.dot(&self.direccion_sol).max(0.0);
+        let visible = ((self.elevacion_relativa() + 0.2) / 0.2).clamp(0.0, 1.0);
+        let cerca_horizonte = 1.0 - self.direccion_sol.y.abs().min(1.0);
+        color += sol * (coseno.powi(8) * (0.25 + 0.5 * cerca_horizonte) * visible);
+
+        if self.tamano_sol > 0.0 && coseno >= self.tamano_sol.cos() {
+            color = sol * visible + color * (1.0 - visible);
+        }
+
+        vec3_a_color(&color)
+    }
+}
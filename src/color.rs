@@ -20,10 +20,19 @@ impl Color {
         Color { r, g, b }
     }
 
-    pub fn to_hex(&self) -> u32 {
+    pub fn to_hex(self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
+    // Los canales de 8 bits estan codificados en sRGB; promediar o mezclar luz se hace en lineal.
+    pub fn a_lineal(self) -> [f32; 3] {
+        [srgb_a_lineal(self.r), srgb_a_lineal(self.g), srgb_a_lineal(self.b)]
+    }
+
+    pub fn desde_lineal(lineal: &[f32; 3]) -> Color {
+        Color::new(lineal_a_srgb(lineal[0]), lineal_a_srgb(lineal[1]), lineal_a_srgb(lineal[2]))
+    }
+
     pub fn lerp(&self, otro: &Color, t: f32) -> Color {
         let r = (self.r as f32 * (1.0 - t) + otro.r as f32 * t) as u8;
         let g = (self.g as f32 * (1.0 - t) + otro.g as f32 * t) as u8;
@@ -37,6 +46,25 @@ impl Color {
     pub fn b(&self) -> u8 { self.b }
 }
 
+fn srgb_a_lineal(valor: u8) -> f32 {
+    let c = valor as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn lineal_a_srgb(valor: f32) -> u8 {
+    let c = valor.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
 impl Add for Color {
     type Output = Color;
 
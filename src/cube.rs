@@ -1,63 +1,36 @@
 use nalgebra_glm::Vec3;
 use crate::material::Material;
 use crate::ray_intersect::{Intersect, RayIntersect};
-use image::RgbaImage;
-use crate::color::Color;
-use crate::light::Light; 
+use crate::light::Light;
+use crate::aabb::Aabb;
+use crate::sampler::{tangentes_cara, uv_cara, TransformacionUv};
 use std::any::Any;
 
 pub struct Cube {
     pub center: Vec3,
     pub size: f32,
     pub materials: [Material; 6], 
+    // Transformacion de UV propia de cada cara; si no hay se usa la del material.
+    pub transformaciones: [Option<TransformacionUv>; 6],
 }
 
 impl Cube {
     pub fn new(center: Vec3, size: f32, materials: [Material; 6]) -> Self {
-        Cube { center, size, materials }
+        Cube { center, size, materials, transformaciones: [None; 6] }
     }
 
-    
+    // Cara (-x, +x, -y, +y, -z, +z) cuyo plano queda mas cerca del punto; en aristas y esquinas gana una sola.
+    fn cara(&self, punto: &Vec3) -> usize {
+        let local = (punto - self.center) / (self.size / 2.0);
 
-    fn get_uv(&self, punto_encuentro: &Vec3) -> (f32, f32) {
-        let mitad = self.size / 2.0;
-        let min = self.center - Vec3::new(mitad, mitad, mitad);
-        let max = self.center + Vec3::new(mitad, mitad, mitad);
-
-        let mut u = 0.0;
-        let mut v = 0.0;
-
-        if (punto_encuentro.x - min.x).abs() < 0.001 { 
-            u = (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.x - max.x).abs() < 0.001 { 
-            u = (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.y - min.y).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
-        } else if (punto_encuentro.y - max.y).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
-        } else if (punto_encuentro.z - min.z).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.z - max.z).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
+        let mut eje = 0;
+        for i in 1..3 {
+            if local[i].abs() > local[eje].abs() {
+                eje = i;
+            }
         }
-        (u, v)
-    }
 
-    fn get_diffuse_color(&self, face_index: usize, u: f32, v: f32) -> Color {
-        if let Some(textura) = &self.materials[face_index].textura {
-            let tex_x = (u * textura.width() as f32) as u32 % textura.width();
-            let tex_y = (v * textura.height() as f32) as u32 % textura.height();
-            let pixel = textura.get_pixel(tex_x, tex_y);
-            Color::new(pixel[0], pixel[1], pixel[2])
-        } else {
-            self.materials[face_index].diffuse.clone()
-        }
+        eje * 2 + if local[eje] > 0.0 { 1 } else { 0 }
     }
 }
 
@@ -81,78 +54,59 @@ impl RayIntersect for Cube {
         let t_hit = if t1 < 0.0 { t2 } else { t1 };
         let punto_encuentro = ray_origin + ray_direction * t_hit;
 
-        let mut normal = Vec3::new(0.0, 0.0, 0.0);
-        let mut face_index = 0;
-
-        for i in 0..3 {
-            if (punto_encuentro[i] - min[i]).abs() < 0.001 {
-                normal[i] = -1.0;
-                face_index = match i {
-                    0 => 0, 
-                    1 => 2, 
-                    2 => 4, 
-                    _ => 0,
-                };
-            } else if (punto_encuentro[i] - max[i]).abs() < 0.001 {
-                normal[i] = 1.0;
-                face_index = match i {
-                    0 => 1, 
-                    1 => 3, 
-                    2 => 5, 
-                    _ => 1,
-                };
-            }
-        }
+        let cara = self.cara(&punto_encuentro);
+        let mut normal = Vec3::zeros();
+        normal[cara / 2] = if cara % 2 == 1 { 1.0 } else { -1.0 };
 
         let (u, v) = self.get_uv(&punto_encuentro);
-        let u = u.clamp(0.0, 1.0);
-        let v = v.clamp(0.0, 1.0);
-
-        let textura_color = self.get_diffuse_color(face_index, u, v);
-
-        Intersect::new(
-            punto_encuentro,
-            normal,
-            t_hit,
-            self.materials[face_index].clone(),
-            u,
-            v
-        )
+
+        let mut material = self.materials[cara].clone();
+        if let Some(transformacion) = self.transformaciones[cara] {
+            material.sampler.transformacion = transformacion;
+        }
+
+        let (tangente, bitangente) = tangentes_cara(cara);
+        Intersect {
+            tamano_uv: self.size,
+            tangente,
+            bitangente,
+            ..Intersect::new(
+                punto_encuentro,
+                normal,
+                t_hit,
+                material,
+                u,
+                v
+            )
+        }
     }
 
     fn get_uv(&self, punto_encuentro: &Vec3) -> (f32, f32) {
-        let mitad = self.size / 2.0;
-        let min = self.center - Vec3::new(mitad, mitad, mitad);
-        let max = self.center + Vec3::new(mitad, mitad, mitad);
-
+        let min = self.center - Vec3::new(self.size, self.size, self.size) / 2.0;
+        let local = (punto_encuentro - min) / self.size;
+        let (u, v) = uv_cara(&local, self.cara(punto_encuentro));
 
-        let mut u = 0.0;
-        let mut v = 0.0;
-
-        if (punto_encuentro.x - min.x).abs() < 0.001 { 
-            u = (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.x - max.x).abs() < 0.001 { 
-            u = (punto_encuentro.z - min.z) / (max.z - min.z);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.y - min.y).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
-        } else if (punto_encuentro.y - max.y).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.z - min.z) / (max.z - min.z);
-        } else if (punto_encuentro.z - min.z).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        } else if (punto_encuentro.z - max.z).abs() < 0.001 { 
-            u = (punto_encuentro.x - min.x) / (max.x - min.x);
-            v = (punto_encuentro.y - min.y) / (max.y - min.y);
-        }
+        (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))
+    }
 
-        (u, v)
+    fn bounding_box(&self) -> Aabb {
+        let mitad = Vec3::repeat(self.size / 2.0);
+        Aabb::new(self.center - mitad, self.center + mitad)
     }
 
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn transparente(&self) -> bool {
+        self.materials.iter().any(|material| material.transparencia > 0.0)
+    }
+
+    // Un cubo suelto no sabe que tiene al lado; se asume que, como en un charco de lava, solo su cara de
+    // arriba queda al aire.
+    fn luces_emisivas(&self) -> Vec<Light> {
+        let arriba = Vec3::new(0.0, 1.0, 0.0);
+        let caras = [(self.center + arriba * (self.size / 2.0), arriba)];
+        self.materials.iter().find_map(|material| material.luz_sobre_caras(&caras, self.size)).into_iter().collect()
+    }
 }
\ No newline at end of file
@@ -1,3 +1,6 @@
+use std::path::Path;
+use image::{ImageResult, Rgb, Rgb32FImage, RgbImage};
+
 
 pub struct Framebuffer {
     pub width: usize,
@@ -37,4 +40,26 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
-}
\ No newline at end of file
+
+    // PNG y PPM guardan el buffer de 8 bits; EXR guarda la radiancia lineal sin cuantizar que se le pasa
+    // (el promedio del acumulador).
+    pub fn guardar(&self, ruta: &Path, radiancia: &[[f32; 3]]) -> ImageResult<()> {
+        let es_exr = ruta
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("exr"));
+
+        if es_exr {
+            let imagen = Rgb32FImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+                Rgb(radiancia[y as usize * self.width + x as usize])
+            });
+            return imagen.save(ruta);
+        }
+
+        let imagen = RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let hex = self.buffer[y as usize * self.width + x as usize];
+            Rgb([(hex >> 16) as u8, (hex >> 8) as u8, hex as u8])
+        });
+        imagen.save(ruta)
+    }
+}
@@ -1,13 +1,23 @@
-
 use nalgebra_glm::Vec3;
 use crate::color::Color;
+use crate::pbr::color_a_vec3;
+use crate::random::Rng;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightKind {
+    Point,
+    Directional { direction: Vec3 },
+    Spot { direction: Vec3, angulo_cono: f32, falloff: f32 },
+}
 
 #[derive(Debug, Clone)]
 pub struct Light {
     pub position: Vec3,
     pub color: Color,
     pub intensity: f32,
-    pub radius: f32
+    pub radius: f32,
+    pub kind: LightKind,
+    pub alcance: f32,
 }
 
 impl Light {
@@ -16,7 +26,129 @@ impl Light {
             position,
             color,
             intensity,
-            radius
+            radius,
+            kind: LightKind::Point,
+            alcance: f32::INFINITY,
+        }
+    }
+
+    pub fn directional(direction: Vec3, color: Color, intensity: f32) -> Self {
+        Light {
+            position: Vec3::zeros(),
+            color,
+            intensity,
+            radius: 0.0,
+            kind: LightKind::Directional { direction: direction.normalize() },
+            alcance: f32::INFINITY,
+        }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, color: Color, intensity: f32, radius: f32, angulo_cono: f32, falloff: f32) -> Self {
+        Light {
+            position,
+            color,
+            intensity,
+            radius,
+            kind: LightKind::Spot { direction: direction.normalize(), angulo_cono, falloff },
+            alcance: f32::INFINITY,
+        }
+    }
+
+    pub fn direccion_hacia(&self, punto: &Vec3) -> Vec3 {
+        match self.kind {
+            LightKind::Directional { direction } => -direction,
+            _ => (self.position - punto).normalize(),
+        }
+    }
+
+    pub fn atenuacion(&self, punto: &Vec3) -> f32 {
+        self.atenuacion_distancia(punto) * self.atenuacion_cono(punto)
+    }
+
+    fn atenuacion_distancia(&self, punto: &Vec3) -> f32 {
+        if !self.alcance.is_finite() {
+            return 1.0;
+        }
+
+        let x = (self.position - punto).magnitude() / self.alcance;
+        (1.0 - x * x).max(0.0).powi(2)
+    }
+
+    fn atenuacion_cono(&self, punto: &Vec3) -> f32 {
+        match self.kind {
+            LightKind::Spot { direction, angulo_cono, falloff } => {
+                let cos_angulo = (punto - self.position).normalize().dot(&direction);
+                let cos_exterior = angulo_cono.cos();
+                let cos_interior = (angulo_cono - falloff).max(0.0).cos();
+
+                if cos_interior <= cos_exterior {
+                    return if cos_angulo >= cos_exterior { 1.0 } else { 0.0 };
+                }
+
+                let t = ((cos_angulo - cos_exterior) / (cos_interior - cos_exterior)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }
+            _ => 1.0,
+        }
+    }
+
+    // Direccion hacia una muestra de la luz y distancia maxima a la que un objeto la tapa.
+    pub fn muestra_sombra(&self, desde: &Vec3, muestra: u32, total: u32, rng: &mut Rng) -> (Vec3, f32) {
+        if let LightKind::Directional { direction } = self.kind {
+            return (-direction, f32::INFINITY);
+        }
+
+        let hacia_luz = self.punto_muestra(desde, muestra, total, rng) - desde;
+        // Lo que queda dentro del radio de la luz es su propio cuerpo (la esfera del sol), no un obstaculo.
+        (hacia_luz.normalize(), hacia_luz.magnitude() - self.radius)
+    }
+
+    // Con una sola muestra el punto igual se elige al azar en el disco, asi el acumulador promedia los
+    // frames quietos hasta llegar a la penumbra.
+    pub fn punto_muestra(&self, desde: &Vec3, muestra: u32, total: u32, rng: &mut Rng) -> Vec3 {
+        if self.radius <= 0.0 {
+            return self.position;
+        }
+
+        let eje = (self.position - desde).normalize();
+        let auxiliar = if eje.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let tangente = eje.cross(&auxiliar).normalize();
+        let bitangente = eje.cross(&tangente);
+
+        let (dx, dy) = rng.disco_estratificado(muestra, total);
+        self.position + (tangente * dx + bitangente * dy) * self.radius
+    }
+}
+
+// Luz que llega de todas partes: una constante mas un hemisferio que mezcla el color del cielo (arriba)
+// con el del suelo (abajo) segun hacia donde mira la normal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ambiente {
+    pub color: Color,
+    pub intensidad: f32,
+    pub cielo: f32,
+    pub suelo: Color,
+}
+
+impl Ambiente {
+    pub fn new() -> Self {
+        Ambiente {
+            color: Color::new(255, 255, 255),
+            intensidad: 1.0,
+            cielo: 0.0,
+            suelo: Color::new(90, 75, 55),
         }
     }
-}
\ No newline at end of file
+
+    pub fn irradiancia(&self, normal: &Vec3, color_cielo: Color) -> Vec3 {
+        let t = normal.y * 0.5 + 0.5;
+        let hemisferio = color_a_vec3(self.suelo).lerp(&color_a_vec3(color_cielo), t);
+        color_a_vec3(self.color) * self.intensidad + hemisferio * self.cielo
+    }
+}
+
+impl Default for Ambiente {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -7,62 +7,158 @@ mod light;
 mod material;
 mod cube;
 mod texturas;
+mod settings;
+mod random;
+mod scene;
+mod aabb;
+mod bvh;
+mod voxel_grid;
+mod opciones;
+mod scene_file;
+mod recarga;
+mod terreno;
+mod acumulacion;
+mod antialiasing;
+mod sampler;
+mod pbr;
+mod trazado;
+mod oclusion;
+mod cielo_procedural;
+mod sombreado;
 use nalgebra_glm::{Vec3, normalize};
-use std::time::{Duration, Instant};
+use std::time::Instant;
+use std::process;
 use std::f32::consts::PI;
-use minifb::{Window, WindowOptions, Key};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use minifb::{Window, WindowOptions, Key, KeyRepeat};
 use crate::color::Color;
 use crate::cube::Cube;
-use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::opciones::{Opciones, USO};
+use crate::scene_file::{cargar_escena, EscenaCargada};
+use crate::recarga::Vigilante;
 use crate::sphere::Sphere;
 use crate::framebuffer::Framebuffer;
+use crate::acumulacion::Acumulador;
 use crate::camera::Camera;
-use crate::light::Light;
-use crate::material::Material;
-use crate::texturas::TextureManager;
-use image::open;
-
-fn reflector(incidente: &Vec3, normal: &Vec3) -> Vec3 {
-    incidente - 2.0 * incidente.dot(normal) * normal
-}
-
-pub fn cast_ray(ray_origin: &Vec3, ray_direction: &Vec3, objects: &[Box<dyn RayIntersect>], luz: &Light, color_fondo: &Color) -> Color {
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = f32::INFINITY;
-
-    for object in objects {
-        let tmp = object.ray_intersect(ray_origin, ray_direction);
-        if tmp.is_intersecting && tmp.distance < zbuffer {
-            zbuffer = tmp.distance;
-            intersect = tmp;
-        }
+use crate::material::Modelo;
+use crate::texturas::{Cono, TextureManager};
+use crate::settings::{ModoRender, RenderSettings};
+use crate::random::Rng;
+use crate::scene::Scene;
+use crate::sombreado::{cast_shadow, fresnel_schlick, offset_origin, preparar_golpe, reflector, refractor};
+
+pub fn cast_ray(ray_origin: &Vec3, ray_direction: &Vec3, cono: Cono, scene: &Scene, settings: &RenderSettings, rng: &mut Rng, depth: u32) -> Color {
+    if depth > settings.max_depth {
+        return scene.fondo(ray_direction);
     }
 
+    let mut intersect = scene.objects.intersect(ray_origin, ray_direction);
+
     if !intersect.is_intersecting {
-        return color_fondo.clone();  
+        return scene.fondo(ray_direction);
     }
 
-    let mut color = intersect.material.diffuse.clone();  
+    let cono = cono.propagar(intersect.distance);
+    let (huella, desde_adentro) = preparar_golpe(&mut intersect, ray_direction, &cono);
 
-    if let Some(ref textura) = intersect.material.textura {
-        color = intersect.material.get_diffuse_color(intersect.u, intersect.v);
+    let color_base = intersect.material.get_diffuse_color(intersect.u, intersect.v, huella);
+    let base = pbr::color_a_vec3(color_base);
+
+    // La luz ambiental sigue al color del cielo; la oclusion ambiental la bloquea en rincones y grietas.
+    let mut ambiente = scene.ambiente.irradiancia(&intersect.normal, scene.color_cielo());
+    if settings.oclusion {
+        ambiente *= 1.0 - oclusion::oclusion_ambiental(&intersect, &scene.objects, settings, rng);
     }
 
-    if let Some(emissive_color) = intersect.material.emisividad_color {
+    // Los metales no tienen difuso, asi que la luz ambiental no los ilumina.
+    let mut color = match intersect.material.modelo {
+        Modelo::Phong => pbr::vec3_a_color(&base.component_mul(&ambiente)),
+        Modelo::Pbr { metalico, .. } => pbr::vec3_a_color(&(base.component_mul(&ambiente) * (1.0 - metalico))),
+    };
+
+    if let Some(emissive_color) = intersect.material.emision() {
         color += emissive_color;
     }
 
-    let light_dir = (luz.position - intersect.point).normalize();
     let view_dir = (ray_origin - intersect.point).normalize();
-    let reflect_dir = reflector(&-light_dir, &intersect.normal);
+    let (exponente_especular, albedo) = intersect.material.propiedades(intersect.u, intersect.v, huella);
+
+    for luz in &scene.luces {
+        let atenuacion = luz.atenuacion(&intersect.point);
+        if atenuacion <= 0.0 {
+            continue;
+        }
+
+        let shadow_intensity = if settings.shadows {
+            cast_shadow(&intersect, luz, &scene.objects, settings, rng)
+        } else {
+            0.0
+        };
+        let light_intensity = luz.intensity * atenuacion * (1.0 - shadow_intensity);
 
-    let diff = intersect.normal.dot(&light_dir).max(0.0);
-    let diffuse = intersect.material.diffuse * intersect.material.albedo[0] * diff * luz.intensity;
+        let light_dir = luz.direccion_hacia(&intersect.point);
 
-    let spec = view_dir.dot(&reflect_dir).max(0.0).powf(intersect.material.specular);
-    let specular = luz.color * intersect.material.albedo[1] * spec * luz.intensity;
+        match intersect.material.modelo {
+            Modelo::Phong => {
+                let reflect_dir = reflector(&-light_dir, &intersect.normal);
+
+                let diff = intersect.normal.dot(&light_dir).max(0.0);
+                let diffuse = intersect.material.diffuse * albedo[0] * diff * light_intensity;
+
+                // Una luz detras de la superficie no deja brillo, aunque el reflejo apunte hacia la camara.
+                let spec = if diff > 0.0 {
+                    view_dir.dot(&reflect_dir).max(0.0).powf(exponente_especular)
+                } else {
+                    0.0
+                };
+                let specular = luz.color * albedo[1] * spec * light_intensity;
+
+                color += diffuse + specular;
+            }
+            Modelo::Pbr { metalico, rugosidad } => {
+                let rugosidad = intersect.material.rugosidad(rugosidad, intersect.u, intersect.v, huella);
+                let brdf = pbr::cook_torrance(&intersect.normal, &view_dir, &light_dir, &base, metalico, rugosidad);
+
+                // La intensidad de la luz es la irradiancia de frente, por eso se multiplica por pi.
+                let radiancia = pbr::color_a_vec3(luz.color) * light_intensity * PI;
+                color += pbr::vec3_a_color(&brdf.component_mul(&radiancia));
+            }
+        }
+    }
+
+    let reflectividad = intersect.material.reflectividad;
+    let transparencia = intersect.material.transparencia;
+    if reflectividad <= 0.0 && transparencia <= 0.0 {
+        return color;
+    }
+
+    let reflection_dir = reflector(ray_direction, &intersect.normal).normalize();
+    let reflect_origin = offset_origin(&intersect, &reflection_dir);
+    let reflect_color = cast_ray(&reflect_origin, &reflection_dir, cono, scene, settings, rng, depth + 1);
+
+    let mut transmitido = Color::new(0, 0, 0);
+    if transparencia > 0.0 {
+        let (eta_i, eta_t) = if desde_adentro {
+            (intersect.material.indice_refraccion, 1.0)
+        } else {
+            (1.0, intersect.material.indice_refraccion)
+        };
+
+        let fresnel = fresnel_schlick(ray_direction, &intersect.normal, eta_i, eta_t);
+        transmitido = match refractor(ray_direction, &intersect.normal, eta_i, eta_t) {
+            Some(refraction_dir) => {
+                let refract_origin = offset_origin(&intersect, &refraction_dir);
+                let refract_color = cast_ray(&refract_origin, &refraction_dir, cono, scene, settings, rng, depth + 1);
+                reflect_color * fresnel + refract_color * (1.0 - fresnel)
+            }
+            None => reflect_color,
+        };
+    }
 
-    color += diffuse + specular;
+    color = color * (1.0 - reflectividad - transparencia).max(0.0)
+        + reflect_color * reflectividad
+        + transmitido * transparencia;
 
     color
 }
@@ -76,809 +172,338 @@ fn transicion_color(inicio: &Color, fin: &Color, t: f32) -> Color {
     Color::new(r, g, b)
 }
 
-pub fn render(framebuffer: &mut Framebuffer, objects: &[Box<dyn RayIntersect>], camera: &Camera, light: &Light, color_fondo: &Color) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
+const TAMANO_TILE: usize = 32;
+
+// Promedio de las muestras del pixel en radiancia lineal.
+fn render_pixel(pixel: (usize, usize), dimensiones: (usize, usize), scene: &Scene, camera: &Camera, settings: &RenderSettings, muestra: u32) -> [f32; 3] {
+    let (x, y) = pixel;
+    let (width, height) = dimensiones;
+
+    // La semilla depende solo del pixel y de la muestra, asi la imagen no cambia con el numero de hilos.
+    let mut rng = Rng::new(((muestra as u64) << 32) ^ (y * width + x) as u64);
+
+    // Sin supersampling la primera muestra va al centro del pixel; el resto sigue el filtro elegido.
+    let desplazamientos = if muestra == 0 && settings.supersampling <= 1 {
+        vec![(0.0, 0.0)]
+    } else {
+        antialiasing::desplazamientos(settings.supersampling, settings.muestreo, settings.filtro, &mut rng)
+    };
+
+    let aspect_ratio = width as f32 / height as f32;
     let fov = PI / 3.0;
     let perspective_scale = (fov * 0.5).tan();
 
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            let screen_x = (2.0 * x as f32) / width - 1.0;
-            let screen_y = -(2.0 * y as f32) / height + 1.0;
+    // Cada rayo cubre un pixel, o una fraccion de el si hay supersampling.
+    let cono = Cono::new(2.0 * perspective_scale / height as f32 / settings.supersampling.max(1) as f32);
 
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
-            let screen_y = screen_y * perspective_scale;
+    let mut suma = [0.0f32; 3];
+    for (desplazamiento_x, desplazamiento_y) in &desplazamientos {
+        let screen_x = (2.0 * (x as f32 + desplazamiento_x)) / width as f32 - 1.0;
+        let screen_y = -(2.0 * (y as f32 + desplazamiento_y)) / height as f32 + 1.0;
 
-            let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
-            let rotated_direction = camera.base_change(&ray_direction);
+        let screen_x = screen_x * aspect_ratio * perspective_scale;
+        let screen_y = screen_y * perspective_scale;
 
-            let pixel_color = cast_ray(&camera.eye, &rotated_direction, objects, light, color_fondo);
+        let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
+        let rotated_direction = camera.base_change(&ray_direction);
 
-            framebuffer.set_current_color(pixel_color.to_hex());
-            framebuffer.point(x, y);
+        let color = match settings.modo {
+            ModoRender::Whitted => cast_ray(&camera.eye, &rotated_direction, cono, scene, settings, &mut rng, 0),
+            ModoRender::Caminos => trazado::trazar(&camera.eye, &rotated_direction, cono, scene, settings, &mut rng),
+            ModoRender::Oclusion => oclusion::trazar_oclusion(&camera.eye, &rotated_direction, cono, scene, settings, &mut rng),
+        };
+        for (canal, valor) in suma.iter_mut().zip(color.a_lineal()) {
+            *canal += valor;
         }
     }
+
+    let total = desplazamientos.len() as f32;
+    [suma[0] / total, suma[1] / total, suma[2] / total]
 }
 
-fn main() {
-    let window_width = 800;
-    let window_height = 600;
-    let framebuffer_width = 800;
-    let framebuffer_height = 600;
-    let frame_delay = Duration::from_millis(16);
-    let intervalo_cambio_color = Duration::from_secs(67); 
-    let duracion_recorrido_luz = Duration::from_secs(10); 
+// Renderiza una muestra mas por pixel en radiancia lineal; el acumulador la promedia con las anteriores
+// y deja el resultado cuantizado en el framebuffer.
+pub fn render(framebuffer: &mut Framebuffer, acumulador: &mut Acumulador, scene: &Scene, camera: &Camera, settings: &RenderSettings) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+    let muestra = acumulador.muestras();
+    let tiles_x = width.div_ceil(TAMANO_TILE);
+    let tiles_y = height.div_ceil(TAMANO_TILE);
+    let total_tiles = tiles_x * tiles_y;
+
+    let siguiente_tile = AtomicUsize::new(0);
+    let hilos = settings.hilos().min(total_tiles.max(1));
+
+    // Cada hilo toma el siguiente tile libre de la cola hasta vaciarla.
+    let tiles: Vec<(usize, Vec<[f32; 3]>)> = thread::scope(|s| {
+        let trabajadores: Vec<_> = (0..hilos)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut terminados = Vec::new();
+                    loop {
+                        let tile = siguiente_tile.fetch_add(1, Ordering::Relaxed);
+                        if tile >= total_tiles {
+                            break;
+                        }
+
+                        let x0 = (tile % tiles_x) * TAMANO_TILE;
+                        let y0 = (tile / tiles_x) * TAMANO_TILE;
+                        let mut colores = Vec::with_capacity(TAMANO_TILE * TAMANO_TILE);
+                        for y in y0..(y0 + TAMANO_TILE).min(height) {
+                            for x in x0..(x0 + TAMANO_TILE).min(width) {
+                                colores.push(render_pixel((x, y), (width, height), scene, camera, settings, muestra));
+                            }
+                        }
+                        terminados.push((tile, colores));
+                    }
+                    terminados
+                })
+            })
+            .collect();
+
+        trabajadores.into_iter().flat_map(|trabajador| trabajador.join().unwrap()).collect()
+    });
+
+    let mut frame = vec![[0.0; 3]; width * height];
+    for (tile, colores) in tiles {
+        let x0 = (tile % tiles_x) * TAMANO_TILE;
+        let y0 = (tile / tiles_x) * TAMANO_TILE;
+        let ancho_tile = (x0 + TAMANO_TILE).min(width) - x0;
+
+        for (i, color) in colores.into_iter().enumerate() {
+            frame[(y0 + i / ancho_tile) * width + x0 + i % ancho_tile] = color;
+        }
+    }
 
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    acumulador.acumular(&frame, framebuffer);
+}
 
-    let mut window = Window::new(
-        "DIORAMA",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    ).unwrap();
+// Todo lo animado depende solo del tiempo, asi el modo headless reproduce los mismos frames que la ventana.
+// Devuelve si algo cambio de verdad, para que el acumulador no se reinicie por frames identicos.
+fn actualizar_animacion(tiempo: f32, escena: &mut EscenaCargada) -> bool {
+    let cielo = &escena.cielo;
+    let progreso_color = (tiempo % cielo.intervalo) / cielo.intervalo;
+    let color_fondo = transicion_color(&cielo.color_inicial, &cielo.color_final, progreso_color);
+    let mut cambio = color_fondo != escena.scene.color_fondo;
+    escena.scene.color_fondo = color_fondo;
+    let mut movido = false;
+
+    if let Some(sol) = &escena.sol {
+        let posicion = sol.posicion(tiempo);
+        let color_esfera = transicion_color(&sol.color_esfera_inicial, &sol.color_esfera_final, progreso_color);
+
+        if escena.scene.luces[sol.indice_luz].position != posicion {
+            movido = true;
+            escena.scene.luces[sol.indice_luz].position = posicion;
+            if let Some(indice) = sol.indice_luz_emisiva {
+                escena.scene.luces[indice].position = posicion;
+            }
+        }
+        if let Some(esfera) = escena.scene.objects.objects_mut()[sol.indice_esfera].as_any_mut().downcast_mut::<Sphere>() {
+            cambio |= esfera.material.diffuse != color_esfera;
+            esfera.center = posicion;
+            esfera.material.diffuse = color_esfera;
+        }
+    }
+
+    // El cielo procedural sigue al sol, y con el la luz ambiental.
+    if let (Some(cielo), Some(sol)) = (&mut escena.scene.cielo, &escena.sol) {
+        cielo.direccion_sol = escena.scene.luces[sol.indice_luz].direccion_hacia(&Vec3::zeros());
+    }
 
-    let rubber = Material::new(
-        Color::new(80, 0, 0),
-        1.0,
-        [0.9, 0.1],
-        None,
-        None,
-    );
-
-    let ivory = Material::new(
-        Color::new(100, 100, 80),
-        50.0,
-        [0.6, 0.3],
-        None,
-        None
-    );
+    for oleaje in &escena.oleaje {
+        if let Some(cube) = escena.scene.objects.objects_mut()[oleaje.indice].as_any_mut().downcast_mut::<Cube>() {
+            let desfase = oleaje.indice as f32 * 0.2;
+            let y = oleaje.base_y + (tiempo * 1.0 + desfase).sin() * 0.2;
+            movido |= cube.center.y != y;
+            cube.center.y = y;
+        }
+    }
+
+    // Solo los objetos animados estan en el arbol que se reconstruye.
+    if movido {
+        escena.scene.objects.reconstruir_dinamicos();
+    }
+
+    cambio || movido
+}
+
+fn main() {
+    let opciones = Opciones::desde_args(std::env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("{}\n{}", error, USO);
+        process::exit(2);
+    });
 
     let mut manejador_textura = TextureManager::new();
-    let imagen_tierra = image::open("images/tierra.png").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("tierra", imagen_tierra);
-    let textura_tierra = manejador_textura.get_textura("tierra");
-
-    let imagen_hoja = image::open("images/hoja_arbol.jpg").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("hoja", imagen_hoja);
-    let textura_hoja = manejador_textura.get_textura("hoja");
-
-    let imagen_tierra_grama = image::open("images/tierra2.png").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("tierra_grama", imagen_tierra_grama);
-    let textura_tierra_grama = manejador_textura.get_textura("tierra_grama");
-
-    let imagen_lava = image::open("images/lava.jpg").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("lava", imagen_lava);
-    let textura_lava = manejador_textura.get_textura("lava");
-
-    let imagen_piedra = image::open("images/piedra.webp").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("piedra", imagen_piedra);
-    let textura_piedra = manejador_textura.get_textura("piedra");
-
-    let imagen_agua = image::open("images/agua.jpg").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("agua", imagen_agua);
-    let textura_agua = manejador_textura.get_textura("agua");
-
-    let imagen_grama = image::open("images/grama.png").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("grama", imagen_grama);
-    let textura_grama = manejador_textura.get_textura("grama");
-
-    let imagen_madera = image::open("images/madera.jpg").unwrap().into_rgba8();
-    manejador_textura.cargar_textura("madera", imagen_madera);
-    let textura_madera = manejador_textura.get_textura("madera");
-
-    let tierra = Material::new(
-        Color::new(101, 67, 33),  
-        0.1, 
-        [0.9, 0.05],
-        textura_tierra.clone(),
-        None
-    );    
-
-    let hojas = Material::new(
-        Color::new(34, 139, 34),  
-        0.2,
-        [0.4, 0.1],  
-        textura_hoja.clone(),
-        None
-    );
-
-    let madera = Material::new(
-        Color::new(139, 69, 19),  
-        0.2,  
-        [0.5, 0.1],  
-        textura_madera,
-        None
-    );
-    
-
-    let tierra_grama = Material::new(
-        Color::new(101, 67, 33),  
-        0.1, 
-        [0.9, 0.05],
-        textura_tierra_grama.clone(),  
-        None
-    );
-
-    let grama = Material::new(
-        Color::new(50, 205, 50), 
-        0.2,  
-        [0.05, 0.1], 
-        textura_grama.clone(),
-        None
-    );
-    
-
-    let lava = Material::new(
-        Color::new(34, 139, 34),  
-        1.0,
-        [0.0, 0.0],  
-        textura_lava.clone(),
-        Some(Color::new(255, 69, 0)),
-    );
-
-    let piedra = Material::new(
-        Color::new(112, 112, 112),  
-        0.15,  
-        [0.75, 0.05],  
-        textura_piedra.clone(),
-        None
-    );    
-    
-    let agua = Material::new(
-        Color::new(64, 164, 223),  
-        0.9,  
-        [0.1, 0.5],  
-        textura_agua.clone(),
-        None
-    );    
-
-    let sol_material = Material::new(
-        Color::new(255, 234, 100), 
-        1.0,
-        [0.0, 0.0],
-        None,
-        None
-    );
-
-    let mut luz = Light::new(
-        Vec3::new(100.0, 100.0, 10.0),
-        Color::new(255, 255, 255),
-        3.5,
-        3.0,
-    );
-
-    let mut esfera_amarilla = Sphere {
-        center: luz.position, 
-        radius: 1.0, 
-        material: sol_material.clone(),
-    };
+    let mut escena = cargar_escena(&opciones.scene, &mut manejador_textura).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+
+    let mut settings = RenderSettings::new();
+    if let Some(shadow_samples) = opciones.shadow_samples {
+        settings.shadow_samples = shadow_samples;
+    }
+    if let Some(threads) = opciones.threads {
+        settings.threads = threads;
+    }
+    if let Some(aa) = opciones.aa {
+        settings.supersampling = aa;
+    }
+    if let Some(filtro) = opciones.filter {
+        settings.filtro = filtro;
+    }
+    if let Some(muestreo) = opciones.sampling {
+        settings.muestreo = muestreo;
+    }
+    if let Some(modo) = opciones.mode {
+        settings.modo = modo;
+    }
+    if let Some(spp) = opciones.spp {
+        settings.muestras_camino = spp;
+    }
+    if let Some(ao) = opciones.ao {
+        settings.oclusion = true;
+        settings.oclusion_muestras = ao;
+    }
+    if let Some(distancia) = opciones.ao_distance {
+        settings.oclusion_distancia = distancia;
+    }
 
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 0.0, 5.0),
-        Vec3::new(0.0, 10.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
+    let mut framebuffer = Framebuffer::new(opciones.width, opciones.height);
+    let mut acumulador = Acumulador::new(opciones.width, opciones.height);
 
-    let rotation_speed = PI / 10.0;
-    let velocidad_movimiento = 0.1;
+    if opciones.headless {
+        for frame in 0..opciones.frames {
+            let tiempo = frame as f32 / opciones.fps;
+            actualizar_animacion(tiempo, &mut escena);
 
-    let color_inicial = Color::new(4, 12, 36);
-    let color_final = Color::new(135, 206, 235);
-    let mut color_actual = color_inicial.clone();
-    let mut siguiente_color = color_final.clone();
-    let mut tiempo_inicial = Instant::now();
-    let mut progreso_transicion = 0.0;
+            acumulador.reiniciar();
+            for _ in 0..opciones.accumulate {
+                render(&mut framebuffer, &mut acumulador, &escena.scene, &escena.camera, &settings);
+            }
 
-    let mut tiempo_luz = Instant::now();
+            let ruta = opciones.ruta_frame(frame);
+            if let Err(error) = framebuffer.guardar(&ruta, &acumulador.promedio()) {
+                eprintln!("no se pudo guardar {}: {}", ruta.display(), error);
+                process::exit(1);
+            }
+            println!("{}", ruta.display());
+        }
+        return;
+    }
 
-    let color_blanco = Color::new(255, 255, 255);
-    let color_amarillo = Color::new(255, 234, 100);
-    let mut color_esfera_actual = color_blanco.clone();
+    let mut window = Window::new(
+        "DIORAMA",
+        opciones.width,
+        opciones.height,
+        WindowOptions::default(),
+    ).unwrap();
 
-    let radio = 100.0;
-    let duracion_recorrido_luz_secs = duracion_recorrido_luz.as_secs_f32();
-    let velocidad_angular = PI / duracion_recorrido_luz_secs;
-    let mut angulo = 0.0;
+    let rotation_speed = PI / 10.0;
+    let velocidad_movimiento = 0.1;
+    let mut tiempo = 0.0;
+    let mut ultimo_frame = Instant::now();
+    let mut en_pausa = false;
+    let mut vigilante = Vigilante::new(escena.archivos.clone());
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
+        let camara_antes = (escena.camera.eye, escena.camera.center, escena.camera.up);
+        let mut escena_cambio = false;
+
+        if vigilante.hubo_cambios() {
+            match cargar_escena(&opciones.scene, &mut manejador_textura) {
+                Ok(mut nueva) => {
+                    std::mem::swap(&mut nueva.camera, &mut escena.camera);
+                    escena = nueva;
+                    vigilante = Vigilante::new(escena.archivos.clone());
+                    escena_cambio = true;
+                    println!("escena recargada: {}", opciones.scene.display());
+                }
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+
         if window.is_key_down(Key::W) {
-            camera.mover_enfrente(velocidad_movimiento);
+            escena.camera.mover_enfrente(velocidad_movimiento);
         }
         if window.is_key_down(Key::S) {
-            camera.mover_atras(velocidad_movimiento);
+            escena.camera.mover_atras(velocidad_movimiento);
         }
         if window.is_key_down(Key::A) {
-            camera.mover_izq(velocidad_movimiento);
+            escena.camera.mover_izq(velocidad_movimiento);
         }
         if window.is_key_down(Key::D) {
-            camera.mover_der(velocidad_movimiento);
+            escena.camera.mover_der(velocidad_movimiento);
         }
 
         if window.is_key_down(Key::Left) {
-            camera.orbit(rotation_speed, 0.0);
+            escena.camera.orbit(rotation_speed, 0.0);
         }
         if window.is_key_down(Key::Right) {
-            camera.orbit(-rotation_speed, 0.0);
+            escena.camera.orbit(-rotation_speed, 0.0);
         }
         if window.is_key_down(Key::Up) {
-            camera.orbit(0.0, -rotation_speed);
+            escena.camera.orbit(0.0, -rotation_speed);
         }
         if window.is_key_down(Key::Down) {
-            camera.orbit(0.0, rotation_speed);
+            escena.camera.orbit(0.0, rotation_speed);
         }
 
-        if Instant::now().duration_since(tiempo_luz) >= frame_delay {
-            angulo += velocidad_angular * frame_delay.as_secs_f32();
-
-            if angulo >= 2.0 * PI {
-                angulo -= 2.0 * PI;
-                let progreso_color = 1.0;
-                color_esfera_actual = transicion_color(&color_blanco, &color_amarillo, progreso_color);
-                siguiente_color = if color_esfera_actual == color_blanco { color_amarillo.clone() } else { color_blanco.clone() };
-            } else {
-                let tiempo_transcurrido = Instant::now().duration_since(tiempo_inicial);
-                let progreso_color = (tiempo_transcurrido.as_secs_f32() / intervalo_cambio_color.as_secs_f32()) % 1.0;
-                color_esfera_actual = transicion_color(&color_blanco, &color_amarillo, progreso_color);
-            }
-
-            luz.position.x = radio * angulo.cos();
-            luz.position.y = 100.0 + radio * angulo.sin();
-            luz.position.z = 10.0;
-
-            esfera_amarilla.center = luz.position;
-            esfera_amarilla.material.diffuse = color_esfera_actual;
-
-            tiempo_luz = Instant::now();
+        if window.is_key_pressed(Key::Key1, KeyRepeat::No) {
+            settings.shadows = !settings.shadows;
+            escena_cambio = true;
+        }
+        if window.is_key_pressed(Key::Key2, KeyRepeat::No) {
+            settings.supersampling = settings.supersampling % 4 + 1;
+            escena_cambio = true;
+            println!("antialiasing: {0}x{0}", settings.supersampling);
+        }
+        if window.is_key_pressed(Key::Key3, KeyRepeat::No) {
+            settings.filtro = settings.filtro.siguiente();
+            escena_cambio = true;
+            println!("filtro: {:?}", settings.filtro);
+        }
+        if window.is_key_pressed(Key::Key4, KeyRepeat::No) {
+            settings.muestreo = settings.muestreo.siguiente();
+            escena_cambio = true;
+            println!("muestreo: {:?}", settings.muestreo);
+        }
+        if window.is_key_pressed(Key::Key5, KeyRepeat::No) {
+            settings.modo = settings.modo.siguiente();
+            escena_cambio = true;
+            println!("modo: {:?}", settings.modo);
+        }
+        if window.is_key_pressed(Key::Key6, KeyRepeat::No) {
+            settings.oclusion = !settings.oclusion;
+            escena_cambio = true;
+        }
+        if window.is_key_pressed(Key::Key7, KeyRepeat::No) {
+            // 1, 4, 16, 64 y de vuelta a 1.
+            settings.shadow_samples = if settings.shadow_samples >= 64 { 1 } else { settings.shadow_samples * 4 };
+            escena_cambio = true;
+            println!("muestras de sombra: {}", settings.shadow_samples);
+        }
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            en_pausa = !en_pausa;
         }
 
-        if Instant::now().duration_since(tiempo_inicial) >= frame_delay {
-            progreso_transicion = (Instant::now().duration_since(tiempo_inicial).as_secs_f32() % intervalo_cambio_color.as_secs_f32()) / intervalo_cambio_color.as_secs_f32();
-            color_actual = transicion_color(&color_inicial, &color_final, progreso_transicion);
+        let delta = ultimo_frame.elapsed().as_secs_f32();
+        ultimo_frame = Instant::now();
+        if !en_pausa {
+            tiempo += delta;
+        }
+        // Tras una recarga hay que volver a aplicar la animacion aunque este en pausa.
+        if !en_pausa || escena_cambio {
+            escena_cambio |= actualizar_animacion(tiempo, &mut escena);
         }
-        let tiempo = tiempo_inicial.elapsed().as_secs_f32();
-        let mut objects: Vec<Box<dyn RayIntersect>> = vec![ 
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -6.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone(), agua.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -6.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -6.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -6.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -6.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -6.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -6.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 6.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 6.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 6.0, -8.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 6.0, -8.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 6.0, -8.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone(), piedra.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(6.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(5.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(4.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(3.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(2.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(1.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -13.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -12.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -11.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -10.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -9.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -8.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(0.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 6.0, -7.0),
-                size: 1.0,
-                materials: [tierra_grama.clone(), tierra_grama.clone(), tierra.clone(), grama.clone(), tierra_grama.clone(), tierra_grama.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 5.0, -7.0),
-                size: 1.0,
-                materials: [tierra.clone(), tierra.clone(), tierra.clone(), tierra.clone(), tierra.clone(), tierra.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 7.0, -8.0),
-                size: 1.0,
-                materials: [madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 8.0, -8.0),
-                size: 1.0,
-                materials: [madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 9.0, -8.0),
-                size: 1.0,
-                materials: [madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone(), madera.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 10.0, -8.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 11.0, -8.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 12.0, -8.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 10.0, -8.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 11.0, -8.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 10.0, -8.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 11.0, -8.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 10.0, -7.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 11.0, -7.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 10.0, -7.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 11.0, -7.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 10.0, -7.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(9.5, 10.0, -9.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 10.0, -9.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(8.5, 11.0, -9.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(Cube {
-                center: Vec3::new(7.5, 10.0, -9.0),
-                size: 1.0,
-                materials: [hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone(), hojas.clone()],
-            }),
-            Box::new(esfera_amarilla.clone()),
-        ];
-
-        for (i, object) in objects.iter_mut().enumerate() {
-            if let Some(cube) = object.as_any_mut().downcast_mut::<Cube>() {
-                if cube.materials.iter().all(|m| m == &agua) {
-                    let desfase = i as f32 * 0.2; 
-                    cube.center.y += (tiempo * 1.0 + desfase).sin() * 0.2;
-                }
-            }
-        }        
 
-        render(&mut framebuffer, &objects, &camera, &luz, &color_actual);
+        // Mientras nada se mueva cada frame aporta una muestra nueva al promedio.
+        if escena_cambio || camara_antes != (escena.camera.eye, escena.camera.center, escena.camera.up) {
+            acumulador.reiniciar();
+        }
+        render(&mut framebuffer, &mut acumulador, &escena.scene, &escena.camera, &settings);
 
         window.update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height).unwrap();
     }
-}
\ No newline at end of file
+}
@@ -1,32 +1,72 @@
 use crate::color::Color;
 use crate::light::Light;
-use image::{RgbaImage, Rgba};
+use crate::sampler::Sampler;
+use crate::texturas::Textura;
+use std::f32::consts::FRAC_1_SQRT_2;
 use std::sync::Arc;
 use nalgebra_glm::Vec3;
 
+const INTENSIDAD_EMISIVA: f32 = 1.5;
+const ALCANCE_EMISIVO: f32 = 4.0;
+
+// Phong usa specular y albedo; Pbr usa Cook-Torrance con el color difuso (o la textura) como color base.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Modelo {
+    Phong,
+    Pbr { metalico: f32, rugosidad: f32 },
+}
+
 #[derive(Debug, Clone, PartialEq)]  
 pub struct Material {
     pub diffuse: Color,
     pub specular: f32,
     pub albedo: [f32; 2],
-    pub textura: Option<Arc<RgbaImage>>, 
+    pub reflectividad: f32,
+    pub transparencia: f32,
+    pub indice_refraccion: f32,
+    pub textura: Option<Arc<Textura>>, 
     pub emisividad_color: Option<Color>, 
+    pub sampler: Sampler,
+    pub mapa_normales: Option<Arc<Textura>>,
+    pub mapa_altura: Option<Arc<Textura>>,
+    pub relieve: f32,
+    pub mapa_especular: Option<Arc<Textura>>,
+    pub mapa_rugosidad: Option<Arc<Textura>>,
+    pub mapa_albedo: Option<Arc<Textura>>,
+    pub modelo: Modelo,
+    pub intensidad_emision: f32,
 }
 
 impl Material {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         diffuse: Color,
         specular: f32,
         albedo: [f32; 2],
-        textura: Option<Arc<RgbaImage>>,
+        reflectividad: f32,
+        transparencia: f32,
+        indice_refraccion: f32,
+        textura: Option<Arc<Textura>>,
         emisividad_color: Option<Color>
     ) -> Self {
         Material {
             diffuse,
             specular,
             albedo,
+            reflectividad,
+            transparencia,
+            indice_refraccion,
             textura,
             emisividad_color,
+            sampler: Sampler::new(),
+            mapa_normales: None,
+            mapa_altura: None,
+            relieve: 1.0,
+            mapa_especular: None,
+            mapa_rugosidad: None,
+            mapa_albedo: None,
+            modelo: Modelo::Phong,
+            intensidad_emision: 1.0,
         }
     }
 
@@ -35,29 +75,125 @@ impl Material {
             diffuse: Color::new(0, 0, 0),
             specular: 0.0,
             albedo: [0.0, 0.0],
+            reflectividad: 0.0,
+            transparencia: 0.0,
+            indice_refraccion: 1.0,
             textura: None,
-            emisividad_color: None
+            emisividad_color: None,
+            sampler: Sampler::new(),
+            mapa_normales: None,
+            mapa_altura: None,
+            relieve: 1.0,
+            mapa_especular: None,
+            mapa_rugosidad: None,
+            mapa_albedo: None,
+            modelo: Modelo::Phong,
+            intensidad_emision: 1.0,
         }
     }
 
-    pub fn get_diffuse_color(&self, u: f32, v: f32) -> Color {
+    pub fn get_diffuse_color(&self, u: f32, v: f32, huella: f32) -> Color {
         if let Some(textura) = &self.textura {
-            let u = u.clamp(0.0, 1.0);
-            let v = v.clamp(0.0, 1.0);
+            return self.sampler.muestrear(textura, u, v, huella);
+        }
+
+        self.diffuse
+    }
+
+    fn brillo(&self, mapa: &Option<Arc<Textura>>, u: f32, v: f32, huella: f32) -> Option<f32> {
+        mapa.as_ref().map(|mapa| {
+            let color = self.sampler.muestrear(mapa, u, v, huella);
+            (color.r() as f32 + color.g() as f32 + color.b() as f32) / (3.0 * 255.0)
+        })
+    }
+
+    // Exponente especular y pesos de albedo en el punto; cada mapa reemplaza a su valor escalar:
+    // mapa_albedo da albedo[0], mapa_especular da albedo[1] y mapa_rugosidad el exponente.
+    pub fn propiedades(&self, u: f32, v: f32, huella: f32) -> (f32, [f32; 2]) {
+        let mut specular = self.specular;
+        let mut albedo = self.albedo;
+
+        if let Some(brillo) = self.brillo(&self.mapa_albedo, u, v, huella) {
+            albedo[0] = brillo;
+        }
+        if let Some(brillo) = self.brillo(&self.mapa_especular, u, v, huella) {
+            albedo[1] = brillo;
+        }
+        if let Some(rugosidad) = self.brillo(&self.mapa_rugosidad, u, v, huella) {
+            // Equivalencia de Blinn-Phong con la rugosidad: alfa = r^2, exponente = 2 / alfa^2 - 2.
+            let alfa = rugosidad.max(0.1).powi(2);
+            specular = (2.0 / (alfa * alfa) - 2.0).max(1.0);
+        }
+
+        (specular, albedo)
+    }
+
+    // En el modelo PBR el mapa de rugosidad reemplaza a la rugosidad del material.
+    pub fn rugosidad(&self, rugosidad: f32, u: f32, v: f32, huella: f32) -> f32 {
+        self.brillo(&self.mapa_rugosidad, u, v, huella).unwrap_or(rugosidad)
+    }
 
-            let v = 1.0 - v;
+    pub fn emision(&self) -> Option<Color> {
+        self.emisividad_color.map(|color| color * self.intensidad_emision)
+    }
 
-            let x = (u * (textura.width() as f32 - 1.0)).floor() as u32;
-            let y = (v * (textura.height() as f32 - 1.0)).floor() as u32;
+    // Normal de sombreado: el mapa de normales esta en espacio tangente (r = u, g = v, b = normal) y el
+    // mapa de altura inclina la normal segun el cambio de brillo entre texeles vecinos.
+    pub fn normal_sombreado(&self, normal: &Vec3, tangente: &Vec3, bitangente: &Vec3, u: f32, v: f32, huella: f32) -> Vec3 {
+        let mut resultado = *normal;
 
-            let x = x.min(textura.width() as u32 - 1);
-            let y = y.min(textura.height() as u32 - 1);
+        if let Some(mapa) = &self.mapa_normales {
+            let color = self.sampler.muestrear(mapa, u, v, huella);
+            let canal = |c: u8| c as f32 / 255.0 * 2.0 - 1.0;
+            resultado = tangente * canal(color.r()) + bitangente * canal(color.g()) + resultado * canal(color.b());
+        }
 
-            let pixel = textura.get_pixel(x, y);
+        if let Some(mapa) = &self.mapa_altura {
+            let paso = (1.0 / mapa.width().max(mapa.height()) as f32).max(huella);
+            let altura = |u: f32, v: f32| {
+                let color = self.sampler.muestrear(mapa, u, v, huella);
+                (color.r() as f32 + color.g() as f32 + color.b() as f32) / (3.0 * 255.0)
+            };
 
-            return Color::new(pixel[0], pixel[1], pixel[2]);
+            let pendiente_u = (altura(u + paso, v) - altura(u - paso, v)) * 0.5;
+            let pendiente_v = (altura(u, v + paso) - altura(u, v - paso)) * 0.5;
+            resultado -= (tangente * pendiente_u + bitangente * pendiente_v) * self.relieve;
         }
 
-        self.diffuse
+        if resultado.magnitude() < 1e-6 {
+            return *normal;
+        }
+        resultado.normalize()
     }
-}
\ No newline at end of file
+
+    // Un objeto emisivo ilumina a sus vecinos como una luz de area con su mismo tamaño.
+    pub fn luz_emisiva(&self, position: Vec3, radius: f32) -> Option<Light> {
+        self.emisividad_color.map(|color| Light {
+            alcance: radius * ALCANCE_EMISIVO,
+            ..Light::new(position, color, INTENSIDAD_EMISIVA * self.intensidad_emision, radius)
+        })
+    }
+
+    // Luz de un bloque o region de bloques emisivos a partir de las caras que dan al aire (centro y normal).
+    // Se pone medio bloque por fuera de ellas: desde el centro del bloque las caras vecinas que estan al
+    // ras quedarian de espaldas a la luz y no recibirian nada.
+    pub fn luz_sobre_caras(&self, caras: &[(Vec3, Vec3)], lado: f32) -> Option<Light> {
+        if caras.is_empty() {
+            return None;
+        }
+
+        let centro = caras.iter().map(|(punto, _)| punto).sum::<Vec3>() / caras.len() as f32;
+        let normal = caras.iter().map(|(_, normal)| normal).sum::<Vec3>();
+        let posicion = if normal.magnitude() > 1e-3 {
+            centro + normal.normalize() * lado * 0.5
+        } else {
+            centro
+        };
+
+        let radio = caras
+            .iter()
+            .map(|(punto, _)| (punto - posicion).magnitude())
+            .fold(0.0, f32::max) + lado * FRAC_1_SQRT_2;
+        self.luz_emisiva(posicion, radio)
+    }
+}
@@ -0,0 +1,43 @@
+use nalgebra_glm::Vec3;
+use crate::bvh::Bvh;
+use crate::color::Color;
+use crate::random::Rng;
+use crate::ray_intersect::Intersect;
+use crate::scene::Scene;
+use crate::settings::RenderSettings;
+use crate::sombreado::{offset_origin, preparar_golpe};
+use crate::texturas::Cono;
+use crate::trazado::direccion_coseno;
+
+// Fraccion del hemisferio de la normal tapada por objetos a menos de la distancia maxima. Con muestreo
+// por coseno cada rayo pesa lo mismo y el resultado ya queda ponderado como la luz difusa. Se usa la
+// normal geometrica: alrededor de la de relieve saldrian rayos por debajo de la superficie.
+pub fn oclusion_ambiental(intersect: &Intersect, objects: &Bvh, settings: &RenderSettings, rng: &mut Rng) -> f32 {
+    let muestras = settings.oclusion_muestras.max(1);
+    let mut bloqueadas = 0;
+
+    for _ in 0..muestras {
+        let direccion = direccion_coseno(&intersect.normal_geometrica, rng);
+        let origen = offset_origin(intersect, &direccion);
+
+        if objects.ocluido(&origen, &direccion, settings.oclusion_distancia) {
+            bloqueadas += 1;
+        }
+    }
+
+    bloqueadas as f32 / muestras as f32
+}
+
+// Modo de depuracion: blanco donde nada tapa la superficie y negro donde queda cubierta por completo.
+pub fn trazar_oclusion(origen: &Vec3, direccion: &Vec3, cono: Cono, scene: &Scene, settings: &RenderSettings, rng: &mut Rng) -> Color {
+    let mut intersect = scene.objects.intersect(origen, direccion);
+    if !intersect.is_intersecting {
+        return Color::new(255, 255, 255);
+    }
+
+    let cono = cono.propagar(intersect.distance);
+    preparar_golpe(&mut intersect, direccion, &cono);
+
+    let gris = ((1.0 - oclusion_ambiental(&intersect, &scene.objects, settings, rng)) * 255.0).round() as u8;
+    Color::new(gris, gris, gris)
+}
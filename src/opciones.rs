@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+use crate::antialiasing::{Filtro, Muestreo};
+use crate::settings::ModoRender;
+
+pub const USO: &str = "uso: proyecto [--scene diorama.toml] [--headless] [--width N] [--height N] [--output ruta.png|.ppm|.exr] [--frames N] [--fps N] [--accumulate N] [--aa N] [--filter box|tent|gauss] [--sampling stratified|random] [--mode whitted|path|ao] [--spp N] [--ao N] [--ao-distance D] [--shadow-samples N] [--threads N]";
+
+pub struct Opciones {
+    pub scene: PathBuf,
+    pub headless: bool,
+    pub width: usize,
+    pub height: usize,
+    pub output: PathBuf,
+    pub frames: u32,
+    pub fps: f32,
+    pub accumulate: u32,
+    pub aa: Option<u32>,
+    pub filter: Option<Filtro>,
+    pub sampling: Option<Muestreo>,
+    pub mode: Option<ModoRender>,
+    pub spp: Option<u32>,
+    pub ao: Option<u32>,
+    pub ao_distance: Option<f32>,
+    pub shadow_samples: Option<u32>,
+    pub threads: Option<usize>,
+}
+
+impl Opciones {
+    pub fn desde_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut opciones = Opciones {
+            scene: PathBuf::from("diorama.toml"),
+            headless: false,
+            width: 800,
+            height: 600,
+            output: PathBuf::from("render.png"),
+            frames: 1,
+            fps: 30.0,
+            accumulate: 1,
+            aa: None,
+            filter: None,
+            sampling: None,
+            mode: None,
+            spp: None,
+            ao: None,
+            ao_distance: None,
+            shadow_samples: None,
+            threads: None,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut valor = || args.next().ok_or_else(|| format!("falta el valor de {}", arg));
+
+            match arg.as_str() {
+                "--scene" => opciones.scene = PathBuf::from(valor()?),
+                "--headless" => opciones.headless = true,
+                "--width" => opciones.width = numero(&arg, &valor()?)?,
+                "--height" => opciones.height = numero(&arg, &valor()?)?,
+                "--output" => opciones.output = PathBuf::from(valor()?),
+                "--frames" => opciones.frames = numero(&arg, &valor()?)?,
+                "--fps" => opciones.fps = numero(&arg, &valor()?)?,
+                "--accumulate" => opciones.accumulate = numero(&arg, &valor()?)?,
+                "--aa" => opciones.aa = Some(numero(&arg, &valor()?)?),
+                "--filter" => opciones.filter = Some(valor()?.parse()?),
+                "--sampling" => opciones.sampling = Some(valor()?.parse()?),
+                "--mode" => opciones.mode = Some(valor()?.parse()?),
+                "--spp" => opciones.spp = Some(numero(&arg, &valor()?)?),
+                "--ao" => opciones.ao = Some(numero(&arg, &valor()?)?),
+                "--ao-distance" => opciones.ao_distance = Some(numero(&arg, &valor()?)?),
+                "--shadow-samples" => opciones.shadow_samples = Some(numero(&arg, &valor()?)?),
+                "--threads" => opciones.threads = Some(numero(&arg, &valor()?)?),
+                otro => return Err(format!("opcion desconocida: {}", otro)),
+            }
+        }
+
+        if opciones.width == 0 || opciones.height == 0 {
+            return Err("la resolucion debe ser mayor que 0".to_string());
+        }
+        if opciones.frames == 0 {
+            return Err("--frames debe ser mayor que 0".to_string());
+        }
+        if opciones.accumulate == 0 {
+            return Err("--accumulate debe ser mayor que 0".to_string());
+        }
+        if opciones.aa == Some(0) {
+            return Err("--aa debe ser mayor que 0".to_string());
+        }
+        if opciones.spp == Some(0) {
+            return Err("--spp debe ser mayor que 0".to_string());
+        }
+        if opciones.ao == Some(0) {
+            return Err("--ao debe ser mayor que 0".to_string());
+        }
+        if opciones.ao_distance.is_some_and(|d| d <= 0.0) {
+            return Err("--ao-distance debe ser mayor que 0".to_string());
+        }
+        if opciones.fps <= 0.0 {
+            return Err("--fps debe ser mayor que 0".to_string());
+        }
+
+        Ok(opciones)
+    }
+
+    // Con varios frames se numera cada archivo: render.png -> render_0000.png, render_0001.png, ...
+    pub fn ruta_frame(&self, frame: u32) -> PathBuf {
+        if self.frames <= 1 {
+            return self.output.clone();
+        }
+
+        let nombre = self.output.file_stem().and_then(|s| s.to_str()).unwrap_or("render");
+        let extension = self.output.extension().and_then(|s| s.to_str()).unwrap_or("png");
+        self.output.with_file_name(format!("{}_{:04}.{}", nombre, frame, extension))
+    }
+}
+
+fn numero<T: std::str::FromStr>(opcion: &str, valor: &str) -> Result<T, String> {
+    valor.parse().map_err(|_| format!("valor invalido para {}: {}", opcion, valor))
+}
@@ -0,0 +1,54 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::color::Color;
+
+pub fn color_a_vec3(color: Color) -> Vec3 {
+    Vec3::new(color.r() as f32, color.g() as f32, color.b() as f32) / 255.0
+}
+
+pub fn vec3_a_color(v: &Vec3) -> Color {
+    let canal = |c: f32| (c * 255.0).round().clamp(0.0, 255.0) as u8;
+    Color::new(canal(v.x), canal(v.y), canal(v.z))
+}
+
+// Distribucion de microfacetas GGX (Trowbridge-Reitz).
+fn distribucion_ggx(n_dot_h: f32, alfa: f32) -> f32 {
+    let alfa2 = alfa * alfa;
+    let d = n_dot_h * n_dot_h * (alfa2 - 1.0) + 1.0;
+    alfa2 / (PI * d * d)
+}
+
+// Sombreado y enmascaramiento de Smith con la aproximacion de Schlick-GGX.
+fn geometria_smith(n_dot_v: f32, n_dot_l: f32, alfa: f32) -> f32 {
+    let k = alfa / 2.0;
+    let g1 = |n_dot_x: f32| n_dot_x / (n_dot_x * (1.0 - k) + k);
+    g1(n_dot_v) * g1(n_dot_l)
+}
+
+pub fn fresnel_schlick(coseno: f32, f0: &Vec3) -> Vec3 {
+    f0 + (Vec3::repeat(1.0) - f0) * (1.0 - coseno).clamp(0.0, 1.0).powi(5)
+}
+
+// BRDF de Cook-Torrance con difuso de Lambert que conserva energia: lo que refleja la parte especular
+// (Fresnel) ya no se difunde, y los metales no tienen difuso. Devuelve la BRDF multiplicada por n·l.
+pub fn cook_torrance(normal: &Vec3, vista: &Vec3, luz: &Vec3, base: &Vec3, metalico: f32, rugosidad: f32) -> Vec3 {
+    let n_dot_l = normal.dot(luz);
+    let n_dot_v = normal.dot(vista);
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+        return Vec3::zeros();
+    }
+
+    let medio = (vista + luz).normalize();
+    let n_dot_h = normal.dot(&medio).max(0.0);
+    let v_dot_h = vista.dot(&medio).max(0.0);
+
+    let alfa = rugosidad.clamp(0.02, 1.0).powi(2);
+    let f0 = Vec3::repeat(0.04).lerp(base, metalico);
+    let fresnel = fresnel_schlick(v_dot_h, &f0);
+
+    let especular = fresnel * distribucion_ggx(n_dot_h, alfa) * geometria_smith(n_dot_v, n_dot_l, alfa)
+        / (4.0 * n_dot_v * n_dot_l).max(1e-4);
+    let difuso = (Vec3::repeat(1.0) - fresnel).component_mul(base) * (1.0 - metalico) / PI;
+
+    (difuso + especular) * n_dot_l
+}
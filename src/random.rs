@@ -0,0 +1,42 @@
+use std::f32::consts::PI;
+
+// Generador xorshift64* pequeño y determinista: la misma semilla da la misma imagen.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Rng { state: if z == 0 { 0x2545_F491_4F6C_DD1D } else { z } }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u32
+    }
+
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub fn disco_estratificado(&mut self, muestra: u32, total: u32) -> (f32, f32) {
+        let lado = (total as f32).sqrt().ceil().max(1.0) as u32;
+        let celda_x = (muestra % lado) as f32;
+        let celda_y = ((muestra / lado) % lado) as f32;
+
+        let u = (celda_x + self.next_f32()) / lado as f32;
+        let v = (celda_y + self.next_f32()) / lado as f32;
+
+        let r = u.sqrt();
+        let theta = 2.0 * PI * v;
+        (r * theta.cos(), r * theta.sin())
+    }
+}
@@ -1,28 +1,42 @@
 use nalgebra_glm::Vec3;
 use crate::material::Material;
+use crate::light::Light;
+use crate::aabb::Aabb;
 use std::any::Any;
 
 #[derive(Debug, Clone)]
 pub struct Intersect {
     pub point: Vec3,
     pub normal: Vec3,
+    pub normal_geometrica: Vec3,
     pub distance: f32,
     pub is_intersecting: bool,
     pub material: Material,
     pub u: f32,
     pub v: f32,
+    pub tamano_uv: f32,
+    pub tangente: Vec3,
+    pub bitangente: Vec3,
 }
 
 impl Intersect {
+    // normal es la de sombreado (con mapas de normales o relieve) y normal_geometrica la de la superficie.
+    // tangente y bitangente apuntan hacia donde crecen u y v.
+    // tamano_uv es la distancia en el mundo que recorre el rango completo de UV, para elegir el mipmap.
     pub fn new(point: Vec3, normal: Vec3, distance: f32, material: Material, u: f32, v: f32) -> Self {
+        let (tangente, bitangente) = marco_tangente(&normal);
         Intersect {
             point,
             normal,
+            normal_geometrica: normal,
             distance,
             is_intersecting: true,
             material,
             u,
             v,
+            tamano_uv: 1.0,
+            tangente,
+            bitangente,
         }
     }
 
@@ -30,17 +44,39 @@ impl Intersect {
         Intersect {
             point: Vec3::zeros(),
             normal: Vec3::zeros(),
+            normal_geometrica: Vec3::zeros(),
             distance: 0.0,
             is_intersecting: false,
             material: Material::black(),
             u: 0.0,
             v: 0.0,
+            tamano_uv: 1.0,
+            tangente: Vec3::zeros(),
+            bitangente: Vec3::zeros(),
         }
     }
 }
 
-pub trait RayIntersect: Any {
+// Base ortonormal cualquiera alrededor de la normal, para primitivas sin direcciones de UV propias.
+pub fn marco_tangente(normal: &Vec3) -> (Vec3, Vec3) {
+    let auxiliar = if normal.x.abs() < 0.9 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+    let tangente = auxiliar.cross(normal).normalize();
+    let bitangente = normal.cross(&tangente);
+    (tangente, bitangente)
+}
+
+pub trait RayIntersect: Any + Send + Sync {
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect;
     fn get_uv(&self, point: &Vec3) -> (f32, f32);
+    fn bounding_box(&self) -> Aabb;
     fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn luces_emisivas(&self) -> Vec<Light> {
+        Vec::new()
+    }
+
+    // Si algun material deja pasar luz; los rayos de sombra solo miden transmision cuando hay alguno.
+    fn transparente(&self) -> bool {
+        false
+    }
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+const INTERVALO_REVISION: Duration = Duration::from_millis(500);
+
+// Revisa por polling la fecha de modificacion de los archivos de la escena.
+pub struct Vigilante {
+    archivos: Vec<(PathBuf, Option<SystemTime>)>,
+    ultima_revision: Instant,
+}
+
+impl Vigilante {
+    pub fn new(archivos: Vec<PathBuf>) -> Self {
+        let archivos = archivos.into_iter().map(|ruta| {
+            let fecha = fecha_modificacion(&ruta);
+            (ruta, fecha)
+        }).collect();
+
+        Vigilante {
+            archivos,
+            ultima_revision: Instant::now(),
+        }
+    }
+
+    pub fn hubo_cambios(&mut self) -> bool {
+        if self.ultima_revision.elapsed() < INTERVALO_REVISION {
+            return false;
+        }
+        self.ultima_revision = Instant::now();
+
+        let mut cambio = false;
+        for (ruta, fecha) in &mut self.archivos {
+            let actual = fecha_modificacion(ruta);
+            if actual != *fecha {
+                *fecha = actual;
+                cambio = true;
+            }
+        }
+        cambio
+    }
+}
+
+fn fecha_modificacion(ruta: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(ruta).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,142 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::texturas::Textura;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FiltroTextura {
+    Cercano,
+    Bilineal,
+    Trilineal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Envoltura {
+    Borde,
+    Repetir,
+    Espejo,
+}
+
+impl Envoltura {
+    pub fn indice(self, i: i64, tamano: u32) -> u32 {
+        let n = tamano as i64;
+        let i = match self {
+            Envoltura::Borde => i.clamp(0, n - 1),
+            Envoltura::Repetir => i.rem_euclid(n),
+            Envoltura::Espejo => {
+                let m = i.rem_euclid(2 * n);
+                if m < n { m } else { 2 * n - 1 - m }
+            }
+        };
+        i as u32
+    }
+}
+
+// Se rota alrededor del centro de la cara, luego se escala y al final se desplaza.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformacionUv {
+    pub rotacion: f32,
+    pub escala: [f32; 2],
+    pub desplazamiento: [f32; 2],
+}
+
+impl TransformacionUv {
+    pub fn identidad() -> Self {
+        TransformacionUv {
+            rotacion: 0.0,
+            escala: [1.0, 1.0],
+            desplazamiento: [0.0, 0.0],
+        }
+    }
+
+    pub fn aplicar(&self, u: f32, v: f32) -> (f32, f32) {
+        let (seno, coseno) = self.rotacion.sin_cos();
+        let (x, y) = (u - 0.5, v - 0.5);
+        let x_rotado = x * coseno - y * seno;
+        let y_rotado = x * seno + y * coseno;
+
+        (
+            x_rotado * self.escala[0] + 0.5 + self.desplazamiento[0],
+            y_rotado * self.escala[1] + 0.5 + self.desplazamiento[1],
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampler {
+    pub filtro: FiltroTextura,
+    pub envoltura: Envoltura,
+    pub transformacion: TransformacionUv,
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        Sampler {
+            filtro: FiltroTextura::Trilineal,
+            envoltura: Envoltura::Borde,
+            transformacion: TransformacionUv::identidad(),
+        }
+    }
+
+    // u crece hacia la derecha y v hacia arriba; la fila 0 de la imagen es la de arriba.
+    // La huella es el tamaño del pixel sobre la superficie medido en unidades de UV.
+    pub fn muestrear(&self, textura: &Textura, u: f32, v: f32, huella: f32) -> Color {
+        let (u, v) = self.transformacion.aplicar(u, v);
+        let v = 1.0 - v;
+
+        match self.filtro {
+            FiltroTextura::Cercano => textura.cercano(0, u, v, self.envoltura),
+            FiltroTextura::Bilineal => textura.bilineal(0, u, v, self.envoltura),
+            FiltroTextura::Trilineal => {
+                let escala = self.transformacion.escala[0].abs().max(self.transformacion.escala[1].abs());
+                let texeles = huella * escala * textura.width().max(textura.height()) as f32;
+                let ultimo = textura.cantidad_niveles() - 1;
+                let nivel = texeles.max(1.0).log2().min(ultimo as f32);
+
+                let inferior = nivel.floor() as usize;
+                let superior = (inferior + 1).min(ultimo);
+                let t = nivel - inferior as f32;
+
+                let a = textura.bilineal(inferior, u, v, self.envoltura);
+                if t <= 0.0 || inferior == superior {
+                    return a;
+                }
+                a.lerp(&textura.bilineal(superior, u, v, self.envoltura), t)
+            }
+        }
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// UV de un punto local ([0, 1] dentro del bloque) sobre la cara indicada (-x, +x, -y, +y, -z, +z).
+// Visto desde afuera, u siempre avanza hacia la derecha y v hacia arriba; en las caras de arriba y
+// abajo "arriba" es -z y +z respectivamente.
+pub fn uv_cara(local: &Vec3, cara: usize) -> (f32, f32) {
+    match cara {
+        0 => (local.z, local.y),
+        1 => (1.0 - local.z, local.y),
+        2 => (local.x, local.z),
+        3 => (local.x, 1.0 - local.z),
+        4 => (1.0 - local.x, local.y),
+        _ => (local.x, local.y),
+    }
+}
+
+// Direcciones en el mundo hacia donde crecen u y v en cada cara, siguiendo a uv_cara.
+pub fn tangentes_cara(cara: usize) -> (Vec3, Vec3) {
+    match cara {
+        0 => (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+        1 => (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0)),
+        2 => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+        3 => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+        4 => (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+        _ => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+    }
+}
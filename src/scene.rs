@@ -0,0 +1,58 @@
+use nalgebra_glm::Vec3;
+use crate::cielo_procedural::CieloProcedural;
+use crate::color::Color;
+use crate::light::{Ambiente, Light};
+use crate::ray_intersect::RayIntersect;
+use crate::bvh::Bvh;
+
+const MAX_LUCES_EMISIVAS: usize = 8;
+
+pub struct Scene {
+    pub objects: Bvh,
+    pub luces: Vec<Light>,
+    // Transicion de [cielo]: fondo plano y color de arriba de la luz de hemisferio sin cielo procedural.
+    pub color_fondo: Color,
+    pub ambiente: Ambiente,
+    pub cielo: Option<CieloProcedural>,
+}
+
+impl Scene {
+    pub fn new(objects: Vec<Box<dyn RayIntersect>>, mut luces: Vec<Light>, color_fondo: Color, ambiente: Ambiente) -> Self {
+        // Las luces de los objetos emisivos van despues de las del archivo. Cada una cuesta sus rayos de
+        // sombra, asi que con muchas (un campo de cubos de lava) solo quedan las mas grandes, en el orden
+        // de los objetos.
+        let mut emisivas: Vec<Light> = objects.iter().flat_map(|object| object.luces_emisivas()).collect();
+        if emisivas.len() > MAX_LUCES_EMISIVAS {
+            let mut orden: Vec<usize> = (0..emisivas.len()).collect();
+            orden.sort_by(|&a, &b| emisivas[b].radius.total_cmp(&emisivas[a].radius));
+            orden.truncate(MAX_LUCES_EMISIVAS);
+            orden.sort_unstable();
+            emisivas = orden.into_iter().map(|i| emisivas[i].clone()).collect();
+        }
+        luces.extend(emisivas);
+
+        Scene {
+            objects: Bvh::new(objects),
+            luces,
+            color_fondo,
+            ambiente,
+            cielo: None,
+        }
+    }
+
+    // Color de los rayos que no chocan con nada.
+    pub fn fondo(&self, direccion: &Vec3) -> Color {
+        match &self.cielo {
+            Some(cielo) => cielo.color(direccion),
+            None => self.color_fondo,
+        }
+    }
+
+    // Color de arriba de la luz de hemisferio, el mismo cielo que se ve de fondo.
+    pub fn color_cielo(&self) -> Color {
+        match &self.cielo {
+            Some(cielo) => cielo.color_ambiente(),
+            None => self.color_fondo,
+        }
+    }
+}
@@ -0,0 +1,971 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use toml::Spanned;
+use crate::camera::Camera;
+use crate::cielo_procedural::CieloProcedural;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::light::{Ambiente, Light};
+use crate::material::{Material, Modelo};
+use crate::ray_intersect::RayIntersect;
+use crate::scene::Scene;
+use crate::sphere::Sphere;
+use crate::terreno::{BloquesTerreno, FuenteAltura, GeneradorTerreno};
+use crate::sampler::{Envoltura, FiltroTextura, TransformacionUv};
+use crate::texturas::{Textura, TextureManager};
+use crate::voxel_grid::VoxelGrid;
+
+// Una cuadricula de 512^3 ya ocupa 256 MB de ids de bloque.
+const MAX_DIMENSION: usize = 4096;
+const MAX_CELDAS: usize = 512 * 512 * 512;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArchivoEscena {
+    camara: CamaraArchivo,
+    cielo: CieloArchivo,
+    sol: Option<SolArchivo>,
+    ambiente: Option<AmbienteArchivo>,
+    #[serde(default)]
+    texturas: BTreeMap<String, Spanned<String>>,
+    #[serde(default)]
+    materiales: BTreeMap<String, MaterialArchivo>,
+    // serde no conserva los spans dentro de un enum con etiqueta, asi que los errores de una luz senalan
+    // la linea donde empieza.
+    #[serde(default)]
+    luces: Vec<Spanned<LuzArchivo>>,
+    #[serde(default)]
+    cubos: Vec<CuboArchivo>,
+    #[serde(default)]
+    esferas: Vec<EsferaArchivo>,
+    #[serde(default)]
+    voxeles: Vec<VoxelesArchivo>,
+    #[serde(default)]
+    terrenos: Vec<TerrenoArchivo>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CamaraArchivo {
+    eye: Spanned<[f32; 3]>,
+    center: Spanned<[f32; 3]>,
+    up: Spanned<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CieloArchivo {
+    color_inicial: [u8; 3],
+    color_final: [u8; 3],
+    intervalo: Spanned<f32>,
+    procedural: Option<CieloProceduralArchivo>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct CieloProceduralArchivo {
+    cenit: [u8; 3],
+    horizonte: [u8; 3],
+    cenit_noche: [u8; 3],
+    horizonte_noche: [u8; 3],
+    color_sol: [u8; 3],
+    tamano_sol: Option<Spanned<f32>>,
+}
+
+impl Default for CieloProceduralArchivo {
+    fn default() -> Self {
+        CieloProceduralArchivo {
+            cenit: [60, 120, 210],
+            horizonte: [175, 210, 235],
+            cenit_noche: [4, 12, 36],
+            horizonte_noche: [20, 30, 60],
+            color_sol: [255, 200, 140],
+            tamano_sol: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AmbienteArchivo {
+    color: Option<[u8; 3]>,
+    intensidad: Option<Spanned<f32>>,
+    cielo: Option<Spanned<f32>>,
+    suelo: Option<[u8; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SolArchivo {
+    material: Spanned<String>,
+    radio_esfera: Spanned<f32>,
+    color: [u8; 3],
+    color_esfera_final: [u8; 3],
+    intensity: Spanned<f32>,
+    radius: Spanned<f32>,
+    radio_orbita: Spanned<f32>,
+    altura: Spanned<f32>,
+    duracion: Spanned<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialArchivo {
+    diffuse: [u8; 3],
+    specular: f32,
+    albedo: [f32; 2],
+    #[serde(default)]
+    reflectividad: f32,
+    #[serde(default)]
+    transparencia: f32,
+    #[serde(default = "indice_refraccion_aire")]
+    indice_refraccion: f32,
+    textura: Option<Spanned<String>>,
+    filtro: Option<FiltroTextura>,
+    envoltura: Option<Envoltura>,
+    uv: Option<UvArchivo>,
+    mapa_normales: Option<Spanned<String>>,
+    mapa_altura: Option<Spanned<String>>,
+    #[serde(default = "relieve_por_defecto")]
+    relieve: f32,
+    mapa_especular: Option<Spanned<String>>,
+    mapa_rugosidad: Option<Spanned<String>>,
+    mapa_albedo: Option<Spanned<String>>,
+    #[serde(default)]
+    modelo: ModeloArchivo,
+    #[serde(default = "metalico_por_defecto")]
+    metalico: Spanned<f32>,
+    #[serde(default = "rugosidad_por_defecto")]
+    rugosidad: Spanned<f32>,
+    emision: Option<[u8; 3]>,
+    #[serde(default = "intensidad_emision_por_defecto")]
+    intensidad_emision: f32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum ModeloArchivo {
+    #[default]
+    Phong,
+    Pbr,
+}
+
+fn metalico_por_defecto() -> Spanned<f32> {
+    Spanned::new(0..0, 0.0)
+}
+
+fn rugosidad_por_defecto() -> Spanned<f32> {
+    Spanned::new(0..0, 0.5)
+}
+
+fn intensidad_emision_por_defecto() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UvArchivo {
+    #[serde(default)]
+    rotacion: f32,
+    #[serde(default = "escala_uv_por_defecto")]
+    escala: [f32; 2],
+    #[serde(default)]
+    desplazamiento: [f32; 2],
+}
+
+impl UvArchivo {
+    fn transformacion(&self) -> TransformacionUv {
+        TransformacionUv {
+            rotacion: self.rotacion.to_radians(),
+            escala: self.escala,
+            desplazamiento: self.desplazamiento,
+        }
+    }
+}
+
+fn relieve_por_defecto() -> f32 {
+    1.0
+}
+
+fn escala_uv_por_defecto() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+fn indice_refraccion_aire() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "tipo", rename_all = "snake_case", deny_unknown_fields)]
+enum LuzArchivo {
+    Punto {
+        position: [f32; 3],
+        color: [u8; 3],
+        intensity: f32,
+        #[serde(default)]
+        radius: f32,
+        alcance: Option<f32>,
+    },
+    Direccional {
+        direction: [f32; 3],
+        color: [u8; 3],
+        intensity: f32,
+    },
+    Foco {
+        position: [f32; 3],
+        direction: [f32; 3],
+        color: [u8; 3],
+        intensity: f32,
+        #[serde(default)]
+        radius: f32,
+        angulo_cono: f32,
+        falloff: f32,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CuboArchivo {
+    center: Spanned<[f32; 3]>,
+    size: Spanned<f32>,
+    materiales: Spanned<Vec<Spanned<String>>>,
+    uv: Option<Spanned<Vec<UvArchivo>>>,
+    #[serde(default)]
+    oleaje: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EsferaArchivo {
+    center: Spanned<[f32; 3]>,
+    radius: Spanned<f32>,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VoxelesArchivo {
+    origen: Spanned<[f32; 3]>,
+    tamano_bloque: Spanned<f32>,
+    dimensiones: Spanned<[usize; 3]>,
+    bloques: BTreeMap<String, Spanned<Vec<Spanned<String>>>>,
+    #[serde(default)]
+    posiciones: BTreeMap<Spanned<String>, Vec<Spanned<[f32; 3]>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TerrenoArchivo {
+    origen: Spanned<[f32; 3]>,
+    tamano_bloque: Spanned<f32>,
+    dimensiones: Spanned<[usize; 3]>,
+    nivel_mar: usize,
+    #[serde(default = "capas_tierra_por_defecto")]
+    capas_tierra: usize,
+    mapa_altura: Option<Spanned<String>>,
+    #[serde(default)]
+    semilla: u64,
+    #[serde(default = "escala_ruido_por_defecto")]
+    escala: Spanned<f32>,
+    #[serde(default = "octavas_por_defecto")]
+    octavas: u32,
+    #[serde(default)]
+    salida: SalidaTerreno,
+    bloques: BloquesTerrenoArchivo,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum SalidaTerreno {
+    #[default]
+    Voxeles,
+    Cubos,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BloquesTerrenoArchivo {
+    grama: Spanned<Vec<Spanned<String>>>,
+    tierra: Spanned<Vec<Spanned<String>>>,
+    piedra: Spanned<Vec<Spanned<String>>>,
+    agua: Spanned<Vec<Spanned<String>>>,
+}
+
+fn capas_tierra_por_defecto() -> usize {
+    3
+}
+
+fn escala_ruido_por_defecto() -> Spanned<f32> {
+    Spanned::new(0..0, 0.08)
+}
+
+fn octavas_por_defecto() -> u32 {
+    4
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Lectura { ruta: PathBuf, error: std::io::Error },
+    Sintaxis { ruta: PathBuf, error: toml::de::Error },
+    Textura { ruta: PathBuf, linea: usize, campo: String, error: image::ImageError },
+    Validacion { ruta: PathBuf, linea: usize, campo: String, mensaje: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Lectura { ruta, error } => write!(f, "{}: {}", ruta.display(), error),
+            SceneError::Sintaxis { ruta, error } => write!(f, "{}: {}", ruta.display(), error),
+            SceneError::Textura { ruta, linea, campo, error } => {
+                write!(f, "{}:{}: {}: no se pudo cargar la textura: {}", ruta.display(), linea, campo, error)
+            }
+            SceneError::Validacion { ruta, linea, campo, mensaje } => {
+                write!(f, "{}:{}: {}: {}", ruta.display(), linea, campo, mensaje)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+pub struct Sol {
+    pub indice_luz: usize,
+    pub indice_esfera: usize,
+    // Luz que registra la esfera si su material es emisivo; tiene que seguirla en la orbita.
+    pub indice_luz_emisiva: Option<usize>,
+    pub color_esfera_inicial: Color,
+    pub color_esfera_final: Color,
+    pub radio_orbita: f32,
+    pub altura: f32,
+    pub duracion: f32,
+}
+
+impl Sol {
+    // Posicion en la orbita a los segundos dados; empieza a la derecha y sube.
+    pub fn posicion(&self, tiempo: f32) -> Vec3 {
+        let angulo = (PI / self.duracion * tiempo) % (2.0 * PI);
+        Vec3::new(
+            self.radio_orbita * angulo.cos(),
+            self.altura + self.radio_orbita * angulo.sin(),
+            10.0,
+        )
+    }
+
+    // Menor y mayor altura de la direccion hacia el sol vista desde el origen a lo largo de una vuelta.
+    pub fn elevacion(&self) -> [f32; 2] {
+        let vuelta = 2.0 * self.duracion;
+        (0..360).fold([f32::INFINITY, f32::NEG_INFINITY], |[minima, maxima], i| {
+            let y = self.posicion(vuelta * i as f32 / 360.0).normalize().y;
+            [minima.min(y), maxima.max(y)]
+        })
+    }
+}
+
+pub struct Cielo {
+    pub color_inicial: Color,
+    pub color_final: Color,
+    pub intervalo: f32,
+}
+
+// Cubo animado con oleaje: indice en la lista de objetos y altura de reposo.
+pub struct Oleaje {
+    pub indice: usize,
+    pub base_y: f32,
+}
+
+pub struct EscenaCargada {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub cielo: Cielo,
+    pub sol: Option<Sol>,
+    pub oleaje: Vec<Oleaje>,
+    pub archivos: Vec<PathBuf>,
+}
+
+struct Validador<'a> {
+    ruta: &'a Path,
+    texto: &'a str,
+}
+
+impl Validador<'_> {
+    fn linea(&self, inicio: usize) -> usize {
+        self.texto[..inicio.min(self.texto.len())].matches('\n').count() + 1
+    }
+
+    fn error<T>(&self, span: std::ops::Range<usize>, campo: impl Into<String>, mensaje: impl Into<String>) -> Result<T, SceneError> {
+        Err(SceneError::Validacion {
+            ruta: self.ruta.to_path_buf(),
+            linea: self.linea(span.start),
+            campo: campo.into(),
+            mensaje: mensaje.into(),
+        })
+    }
+
+    fn material(&self, materiales: &BTreeMap<String, Material>, nombre: &Spanned<String>, campo: &str) -> Result<Material, SceneError> {
+        match materiales.get(nombre.get_ref()) {
+            Some(material) => Ok(material.clone()),
+            None => self.error(nombre.span(), campo, format!("material desconocido '{}'", nombre.get_ref())),
+        }
+    }
+
+    fn textura(&self, manejador_textura: &TextureManager, nombre: &Option<Spanned<String>>, campo: &str) -> Result<Option<Arc<Textura>>, SceneError> {
+        let Some(nombre) = nombre else {
+            return Ok(None);
+        };
+
+        match manejador_textura.get_textura(nombre.get_ref()) {
+            Some(textura) => Ok(Some(textura)),
+            None => self.error(nombre.span(), campo, format!("textura desconocida '{}'", nombre.get_ref())),
+        }
+    }
+
+    // Se aceptan 1 material (todas las caras) o 6 (-x, +x, -y, +y, -z, +z).
+    fn caras(&self, materiales: &BTreeMap<String, Material>, nombres: &Spanned<Vec<Spanned<String>>>, campo: &str) -> Result<[Material; 6], SceneError> {
+        let lista = nombres.get_ref();
+        if lista.len() != 1 && lista.len() != 6 {
+            return self.error(nombres.span(), campo, format!("se esperaban 1 o 6 materiales, hay {}", lista.len()));
+        }
+
+        let mut caras = Vec::with_capacity(6);
+        for i in 0..6 {
+            let nombre = &lista[if lista.len() == 1 { 0 } else { i }];
+            caras.push(self.material(materiales, nombre, campo)?);
+        }
+
+        Ok(caras.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+
+    fn positivo(&self, valor: &Spanned<f32>, campo: &str) -> Result<f32, SceneError> {
+        let v = *valor.get_ref();
+        if !v.is_finite() || v <= 0.0 {
+            return self.error(valor.span(), campo, "debe ser un numero finito mayor que 0");
+        }
+        Ok(v)
+    }
+
+    fn no_negativo(&self, valor: &Spanned<f32>, campo: &str) -> Result<f32, SceneError> {
+        let v = *valor.get_ref();
+        if !v.is_finite() || v < 0.0 {
+            return self.error(valor.span(), campo, "debe ser un numero finito mayor o igual que 0");
+        }
+        Ok(v)
+    }
+
+    fn numero(&self, valor: &Spanned<f32>, campo: &str) -> Result<f32, SceneError> {
+        if !valor.get_ref().is_finite() {
+            return self.error(valor.span(), campo, "debe ser un numero finito");
+        }
+        Ok(*valor.get_ref())
+    }
+
+    fn direccion(&self, valor: &Spanned<[f32; 3]>, campo: &str) -> Result<Vec3, SceneError> {
+        let direccion = self.punto(valor, campo)?;
+        if direccion.magnitude() < 1e-6 {
+            return self.error(valor.span(), campo, "la direccion no puede ser el vector cero");
+        }
+        Ok(direccion)
+    }
+
+    fn punto(&self, valor: &Spanned<[f32; 3]>, campo: &str) -> Result<Vec3, SceneError> {
+        if !valor.get_ref().iter().all(|c| c.is_finite()) {
+            return self.error(valor.span(), campo, "todas las coordenadas deben ser finitas");
+        }
+        Ok(vec3(*valor.get_ref()))
+    }
+
+    // Limita el tamaño para que un error de tipeo de un mensaje en vez de abortar al reservar memoria.
+    fn dimensiones(&self, valor: &Spanned<[usize; 3]>, campo: &str) -> Result<[usize; 3], SceneError> {
+        let dimensiones = *valor.get_ref();
+        if dimensiones.contains(&0) {
+            return self.error(valor.span(), campo, "todas las dimensiones deben ser mayores que 0");
+        }
+        if dimensiones.iter().any(|&d| d > MAX_DIMENSION) {
+            return self.error(valor.span(), campo, format!("ninguna dimension puede pasar de {}", MAX_DIMENSION));
+        }
+        if dimensiones.iter().product::<usize>() > MAX_CELDAS {
+            return self.error(valor.span(), campo, format!("la cuadricula no puede tener mas de {} celdas", MAX_CELDAS));
+        }
+        Ok(dimensiones)
+    }
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn color(c: [u8; 3]) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+pub fn cargar_escena(ruta: &Path, manejador_textura: &mut TextureManager) -> Result<EscenaCargada, SceneError> {
+    let texto = fs::read_to_string(ruta).map_err(|error| SceneError::Lectura { ruta: ruta.to_path_buf(), error })?;
+    interpretar_escena(ruta, &texto, manejador_textura)
+}
+
+// Los errores se reportan con la ruta dada; las texturas se buscan relativas a ella.
+fn interpretar_escena(ruta: &Path, texto: &str, manejador_textura: &mut TextureManager) -> Result<EscenaCargada, SceneError> {
+    let archivo: ArchivoEscena = toml::from_str(texto).map_err(|error| SceneError::Sintaxis { ruta: ruta.to_path_buf(), error })?;
+    let validador = Validador { ruta, texto };
+    let carpeta = ruta.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut archivos = vec![ruta.to_path_buf()];
+    // Las texturas nuevas se cargan aparte y solo reemplazan a las anteriores si toda la escena es valida.
+    let mut texturas = TextureManager::new();
+
+    for (nombre, archivo_textura) in &archivo.texturas {
+        let ruta_textura = carpeta.join(archivo_textura.get_ref());
+        archivos.push(ruta_textura.clone());
+
+        let imagen = image::open(ruta_textura).map_err(|error| SceneError::Textura {
+            ruta: ruta.to_path_buf(),
+            linea: validador.linea(archivo_textura.span().start),
+            campo: format!("texturas.{}", nombre),
+            error,
+        })?;
+        texturas.cargar_textura(nombre, imagen.into_rgba8());
+    }
+
+    let mut materiales = BTreeMap::new();
+    for (nombre, m) in &archivo.materiales {
+        let textura = validador.textura(&texturas, &m.textura, &format!("materiales.{}.textura", nombre))?;
+
+        let mut material = Material::new(
+            color(m.diffuse),
+            m.specular,
+            m.albedo,
+            m.reflectividad,
+            m.transparencia,
+            m.indice_refraccion,
+            textura,
+            m.emision.map(color),
+        );
+        if let Some(filtro) = m.filtro {
+            material.sampler.filtro = filtro;
+        }
+        if let Some(envoltura) = m.envoltura {
+            material.sampler.envoltura = envoltura;
+        }
+        material.mapa_normales = validador.textura(&texturas, &m.mapa_normales, &format!("materiales.{}.mapa_normales", nombre))?;
+        material.mapa_altura = validador.textura(&texturas, &m.mapa_altura, &format!("materiales.{}.mapa_altura", nombre))?;
+        material.relieve = m.relieve;
+        material.intensidad_emision = m.intensidad_emision;
+        if let ModeloArchivo::Pbr = m.modelo {
+            for (valor, campo) in [(&m.metalico, "metalico"), (&m.rugosidad, "rugosidad")] {
+                if !(0.0..=1.0).contains(valor.get_ref()) {
+                    return validador.error(valor.span(), format!("materiales.{}.{}", nombre, campo), "debe estar entre 0 y 1");
+                }
+            }
+            material.modelo = Modelo::Pbr { metalico: *m.metalico.get_ref(), rugosidad: *m.rugosidad.get_ref() };
+        }
+        material.mapa_especular = validador.textura(&texturas, &m.mapa_especular, &format!("materiales.{}.mapa_especular", nombre))?;
+        material.mapa_rugosidad = validador.textura(&texturas, &m.mapa_rugosidad, &format!("materiales.{}.mapa_rugosidad", nombre))?;
+        material.mapa_albedo = validador.textura(&texturas, &m.mapa_albedo, &format!("materiales.{}.mapa_albedo", nombre))?;
+        if let Some(uv) = &m.uv {
+            material.sampler.transformacion = uv.transformacion();
+        }
+        materiales.insert(nombre.clone(), material);
+    }
+
+    let cielo = &archivo.cielo;
+    let intervalo = validador.positivo(&cielo.intervalo, "cielo.intervalo")?;
+    if let Some(tamano) = cielo.procedural.as_ref().and_then(|p| p.tamano_sol.as_ref()) {
+        if !(0.0..90.0).contains(tamano.get_ref()) {
+            return validador.error(tamano.span(), "cielo.procedural.tamano_sol", "debe estar entre 0 y 90 grados");
+        }
+    }
+
+    let mut ambiente = Ambiente::new();
+    if let Some(a) = &archivo.ambiente {
+        for (valor, campo) in [(&a.intensidad, "ambiente.intensidad"), (&a.cielo, "ambiente.cielo")] {
+            if let Some(valor) = valor {
+                validador.no_negativo(valor, campo)?;
+            }
+        }
+
+        if let Some(c) = a.color {
+            ambiente.color = color(c);
+        }
+        if let Some(intensidad) = &a.intensidad {
+            ambiente.intensidad = *intensidad.get_ref();
+        }
+        if let Some(cielo) = &a.cielo {
+            ambiente.cielo = *cielo.get_ref();
+        }
+        if let Some(suelo) = a.suelo {
+            ambiente.suelo = color(suelo);
+        }
+    }
+
+    let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+    let mut luces = Vec::new();
+    let mut sol = None;
+
+    if let Some(s) = &archivo.sol {
+        let duracion = validador.positivo(&s.duracion, "sol.duracion")?;
+        let radio_esfera = validador.positivo(&s.radio_esfera, "sol.radio_esfera")?;
+        let intensity = validador.no_negativo(&s.intensity, "sol.intensity")?;
+        let radius = validador.no_negativo(&s.radius, "sol.radius")?;
+        let radio_orbita = validador.no_negativo(&s.radio_orbita, "sol.radio_orbita")?;
+        let altura = validador.numero(&s.altura, "sol.altura")?;
+
+        let material = validador.material(&materiales, &s.material, "sol.material")?;
+        let color_esfera_inicial = material.diffuse;
+
+        let nuevo = Sol {
+            indice_luz: luces.len(),
+            indice_esfera: objects.len(),
+            indice_luz_emisiva: None,
+            color_esfera_inicial,
+            color_esfera_final: color(s.color_esfera_final),
+            radio_orbita,
+            altura,
+            duracion,
+        };
+        let posicion = nuevo.posicion(0.0);
+        sol = Some(nuevo);
+
+        luces.push(Light::new(posicion, color(s.color), intensity, radius));
+        objects.push(Box::new(Sphere {
+            center: posicion,
+            radius: radio_esfera,
+            material,
+        }));
+    }
+
+    for (i, luz) in archivo.luces.iter().enumerate() {
+        let campo = |nombre: &str| format!("luces[{}].{}", i, nombre);
+        let en_luz = |valor| Spanned::new(luz.span(), valor);
+
+        luces.push(match *luz.get_ref() {
+            LuzArchivo::Punto { position, color: c, intensity, radius, alcance } => {
+                let alcance = match alcance {
+                    Some(alcance) => validador.positivo(&en_luz(alcance), &campo("alcance"))?,
+                    None => f32::INFINITY,
+                };
+                Light {
+                    alcance,
+                    ..Light::new(
+                        validador.punto(&Spanned::new(luz.span(), position), &campo("position"))?,
+                        color(c),
+                        validador.no_negativo(&en_luz(intensity), &campo("intensity"))?,
+                        validador.no_negativo(&en_luz(radius), &campo("radius"))?,
+                    )
+                }
+            }
+            LuzArchivo::Direccional { direction, color: c, intensity } => Light::directional(
+                validador.direccion(&Spanned::new(luz.span(), direction), &campo("direction"))?,
+                color(c),
+                validador.no_negativo(&en_luz(intensity), &campo("intensity"))?,
+            ),
+            LuzArchivo::Foco { position, direction, color: c, intensity, radius, angulo_cono, falloff } => {
+                if !(angulo_cono > 0.0 && angulo_cono <= 180.0) {
+                    return validador.error(luz.span(), campo("angulo_cono"), "debe estar entre 0 (sin incluir) y 180 grados");
+                }
+                if !(0.0..=angulo_cono).contains(&falloff) {
+                    return validador.error(luz.span(), campo("falloff"), "debe estar entre 0 y angulo_cono");
+                }
+
+                Light::spot(
+                    validador.punto(&Spanned::new(luz.span(), position), &campo("position"))?,
+                    validador.direccion(&Spanned::new(luz.span(), direction), &campo("direction"))?,
+                    color(c),
+                    validador.no_negativo(&en_luz(intensity), &campo("intensity"))?,
+                    validador.no_negativo(&en_luz(radius), &campo("radius"))?,
+                    angulo_cono.to_radians(),
+                    falloff.to_radians(),
+                )
+            }
+        });
+    }
+
+    for (i, v) in archivo.voxeles.iter().enumerate() {
+        let dimensiones = validador.dimensiones(&v.dimensiones, &format!("voxeles[{}].dimensiones", i))?;
+        let tamano_bloque = validador.positivo(&v.tamano_bloque, &format!("voxeles[{}].tamano_bloque", i))?;
+        let origen = validador.punto(&v.origen, &format!("voxeles[{}].origen", i))?;
+
+        let mut grid = VoxelGrid::new(origen, tamano_bloque, dimensiones);
+        let mut ids = BTreeMap::new();
+        for (nombre, caras) in &v.bloques {
+            let caras = validador.caras(&materiales, caras, &format!("voxeles[{}].bloques.{}", i, nombre))?;
+            ids.insert(nombre.clone(), grid.registrar_bloque(caras));
+        }
+
+        for (nombre, posiciones) in &v.posiciones {
+            let campo = format!("voxeles[{}].posiciones.{}", i, nombre.get_ref());
+            let Some(&id) = ids.get(nombre.get_ref()) else {
+                return validador.error(nombre.span(), campo, format!("bloque desconocido '{}'", nombre.get_ref()));
+            };
+
+            for posicion in posiciones {
+                if !grid.contiene(&vec3(*posicion.get_ref())) {
+                    return validador.error(posicion.span(), campo, "la posicion queda fuera de la cuadricula");
+                }
+                grid.set_bloque_en(&vec3(*posicion.get_ref()), id);
+            }
+        }
+
+        objects.push(Box::new(grid));
+    }
+
+    for (i, t) in archivo.terrenos.iter().enumerate() {
+        let [dx, dy, dz] = validador.dimensiones(&t.dimensiones, &format!("terrenos[{}].dimensiones", i))?;
+        let tamano_bloque = validador.positivo(&t.tamano_bloque, &format!("terrenos[{}].tamano_bloque", i))?;
+        let origen = validador.punto(&t.origen, &format!("terrenos[{}].origen", i))?;
+
+        let fuente = match &t.mapa_altura {
+            Some(mapa) => {
+                let ruta_mapa = carpeta.join(mapa.get_ref());
+                archivos.push(ruta_mapa.clone());
+
+                let imagen = image::open(ruta_mapa).map_err(|error| SceneError::Textura {
+                    ruta: ruta.to_path_buf(),
+                    linea: validador.linea(mapa.span().start),
+                    campo: format!("terrenos[{}].mapa_altura", i),
+                    error,
+                })?;
+                FuenteAltura::Mapa(imagen.into_luma8())
+            }
+            None => {
+                let escala = validador.positivo(&t.escala, &format!("terrenos[{}].escala", i))?;
+                FuenteAltura::Ruido { semilla: t.semilla, escala, octavas: t.octavas }
+            }
+        };
+
+        let campo = |nombre: &str| format!("terrenos[{}].bloques.{}", i, nombre);
+        let bloques = BloquesTerreno {
+            grama: validador.caras(&materiales, &t.bloques.grama, &campo("grama"))?,
+            tierra: validador.caras(&materiales, &t.bloques.tierra, &campo("tierra"))?,
+            piedra: validador.caras(&materiales, &t.bloques.piedra, &campo("piedra"))?,
+            agua: validador.caras(&materiales, &t.bloques.agua, &campo("agua"))?,
+        };
+
+        let generador = GeneradorTerreno {
+            dimensiones: [dx, dy, dz],
+            nivel_mar: t.nivel_mar,
+            capas_tierra: t.capas_tierra,
+            fuente,
+        };
+
+        match t.salida {
+            SalidaTerreno::Voxeles => {
+                objects.push(Box::new(generador.generar_voxeles(origen, tamano_bloque, &bloques)));
+            }
+            SalidaTerreno::Cubos => {
+                for cubo in generador.generar_cubos(origen, tamano_bloque, &bloques) {
+                    objects.push(Box::new(cubo));
+                }
+            }
+        }
+    }
+
+    let mut oleaje = Vec::new();
+    for (i, c) in archivo.cubos.iter().enumerate() {
+        let materials = validador.caras(&materiales, &c.materiales, &format!("cubos[{}].materiales", i))?;
+        let center = validador.punto(&c.center, &format!("cubos[{}].center", i))?;
+        let size = validador.positivo(&c.size, &format!("cubos[{}].size", i))?;
+        if c.oleaje {
+            oleaje.push(Oleaje { indice: objects.len(), base_y: center.y });
+        }
+        let mut cubo = Cube::new(center, size, materials);
+        if let Some(uv) = &c.uv {
+            let lista = uv.get_ref();
+            if lista.len() != 1 && lista.len() != 6 {
+                return validador.error(uv.span(), format!("cubos[{}].uv", i), format!("se esperaban 1 o 6 transformaciones, hay {}", lista.len()));
+            }
+            for (cara, transformacion) in cubo.transformaciones.iter_mut().enumerate() {
+                *transformacion = Some(lista[if lista.len() == 1 { 0 } else { cara }].transformacion());
+            }
+        }
+        objects.push(Box::new(cubo));
+    }
+
+    for (i, e) in archivo.esferas.iter().enumerate() {
+        let material = validador.material(&materiales, &e.material, &format!("esferas[{}].material", i))?;
+        objects.push(Box::new(Sphere {
+            center: validador.punto(&e.center, &format!("esferas[{}].center", i))?,
+            radius: validador.positivo(&e.radius, &format!("esferas[{}].radius", i))?,
+            material,
+        }));
+    }
+
+    // Con eye en center o up en la linea de vista la base de la camara no existe y los rayos salen NaN.
+    let camara = &archivo.camara;
+    let eye = validador.punto(&camara.eye, "camara.eye")?;
+    let center = validador.punto(&camara.center, "camara.center")?;
+    let up = validador.direccion(&camara.up, "camara.up")?;
+    let vista = center - eye;
+    if vista.magnitude() < 1e-6 {
+        return validador.error(camara.center.span(), "camara.center", "no puede coincidir con eye");
+    }
+    if vista.normalize().cross(&up.normalize()).magnitude() < 1e-4 {
+        return validador.error(camara.up.span(), "camara.up", "no puede ser paralelo a la direccion de la camara");
+    }
+    // El sol del cielo sale de la primera luz hasta que la animacion lo mueve; sin [sol] no hay recorrido
+    // y la noche empieza en el horizonte.
+    let procedural = cielo.procedural.as_ref().map(|p| CieloProcedural {
+        cenit: color(p.cenit),
+        horizonte: color(p.horizonte),
+        cenit_noche: color(p.cenit_noche),
+        horizonte_noche: color(p.horizonte_noche),
+        color_sol: color(p.color_sol),
+        tamano_sol: p.tamano_sol.as_ref().map_or(0.0, |t| t.get_ref().to_radians()),
+        direccion_sol: luces.first().map_or(Vec3::new(0.0, 1.0, 0.0), |luz| luz.direccion_hacia(&Vec3::zeros())),
+        elevacion_sol: sol.as_ref().map_or([-1.0, 1.0], Sol::elevacion),
+    });
+
+    let cielo = Cielo {
+        color_inicial: color(cielo.color_inicial),
+        color_final: color(cielo.color_final),
+        intervalo,
+    };
+
+    let luces_archivo = luces.len();
+    let mut scene = Scene::new(objects, luces, cielo.color_inicial, ambiente);
+    scene.cielo = procedural;
+
+    // La esfera del sol y los cubos con oleaje se mueven en cada frame; el resto del BVH queda fijo.
+    let dinamicos: Vec<usize> = sol.iter().map(|s| s.indice_esfera).chain(oleaje.iter().map(|o| o.indice)).collect();
+    scene.objects.marcar_dinamicos(&dinamicos);
+
+    // Scene::new agrega las luces emisivas despues de las del archivo y en el orden de los objetos; la
+    // esfera del sol es el primer objeto, asi que su luz, si la tiene y no quedo fuera del limite, es la
+    // primera de ellas y esta en el centro de la esfera.
+    if let Some(sol) = &mut sol {
+        let centro = sol.posicion(0.0);
+        if scene.luces.get(luces_archivo).is_some_and(|luz| luz.position == centro) {
+            sol.indice_luz_emisiva = Some(luces_archivo);
+        }
+    }
+
+    *manejador_textura = texturas;
+
+    Ok(EscenaCargada {
+        scene,
+        camera: Camera::new(eye, center, up),
+        cielo,
+        sol,
+        oleaje,
+        archivos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+[camara]
+eye = [0.0, 0.0, 5.0]
+center = [0.0, 0.0, 0.0]
+up = [0.0, 1.0, 0.0]
+
+[cielo]
+color_inicial = [0, 0, 0]
+color_final = [255, 255, 255]
+intervalo = 10.0
+
+[materiales.piedra]
+diffuse = [112, 112, 112]
+specular = 0.15
+albedo = [0.75, 0.05]
+"#;
+
+    fn cargar(texto: &str) -> Result<EscenaCargada, SceneError> {
+        interpretar_escena(Path::new("prueba.toml"), texto, &mut TextureManager::new())
+    }
+
+    fn error(texto: &str) -> String {
+        match cargar(texto) {
+            Ok(_) => panic!("la escena deberia ser invalida:\n{}", texto),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    // La base con una linea reemplazada y lo extra agregado al final. BASE empieza con una linea vacia, asi
+    // que la primera tabla de lo extra queda en la linea 17.
+    fn escena(reemplazo: Option<(&str, &str)>, extra: &str) -> String {
+        let mut texto = BASE.to_string();
+        if let Some((antes, despues)) = reemplazo {
+            assert!(texto.contains(antes));
+            texto = texto.replace(antes, despues);
+        }
+        texto + extra
+    }
+
+    #[test]
+    fn la_escena_base_es_valida() {
+        let escena = cargar(BASE).unwrap_or_else(|error| panic!("{}", error));
+        assert!(escena.scene.luces.is_empty());
+        assert_eq!(escena.camera.eye, Vec3::new(0.0, 0.0, 5.0));
+    }
+
+    #[test]
+    fn rechaza_una_luz_direccional_sin_direccion() {
+        let texto = escena(None, "\n[[luces]]\ntipo = \"direccional\"\ndirection = [0.0, 0.0, 0.0]\ncolor = [255, 255, 255]\nintensity = 1.0\n");
+        assert_eq!(error(&texto), "prueba.toml:17: luces[0].direction: la direccion no puede ser el vector cero");
+    }
+
+    #[test]
+    fn rechaza_angulos_de_foco_fuera_de_rango() {
+        let foco = |angulo: &str, falloff: &str| {
+            escena(None, &format!(
+                "\n[[luces]]\ntipo = \"foco\"\nposition = [0.0, 5.0, 0.0]\ndirection = [0.0, -1.0, 0.0]\ncolor = [255, 255, 255]\nintensity = 1.0\nangulo_cono = {}\nfalloff = {}\n",
+                angulo, falloff
+            ))
+        };
+
+        assert_eq!(error(&foco("0.0", "0.0")), "prueba.toml:17: luces[0].angulo_cono: debe estar entre 0 (sin incluir) y 180 grados");
+        assert_eq!(error(&foco("nan", "0.0")), "prueba.toml:17: luces[0].angulo_cono: debe estar entre 0 (sin incluir) y 180 grados");
+        assert_eq!(error(&foco("30.0", "45.0")), "prueba.toml:17: luces[0].falloff: debe estar entre 0 y angulo_cono");
+    }
+
+    #[test]
+    fn rechaza_una_camara_sin_direccion() {
+        let texto = escena(Some(("center = [0.0, 0.0, 0.0]", "center = [0.0, 0.0, 5.0]")), "");
+        assert_eq!(error(&texto), "prueba.toml:4: camara.center: no puede coincidir con eye");
+
+        let texto = escena(Some(("up = [0.0, 1.0, 0.0]", "up = [0.0, 0.0, 2.0]")), "");
+        assert_eq!(error(&texto), "prueba.toml:5: camara.up: no puede ser paralelo a la direccion de la camara");
+    }
+
+    #[test]
+    fn rechaza_valores_negativos_del_sol() {
+        let sol = |campo: &str, valor: &str| {
+            let mut texto = escena(None, "\n[sol]\nmaterial = \"piedra\"\nradio_esfera = 1.0\ncolor = [255, 255, 255]\ncolor_esfera_final = [255, 234, 100]\nintensity = 3.5\nradius = 3.0\nradio_orbita = 100.0\naltura = 100.0\nduracion = 10.0\n");
+            let linea = texto.lines().find(|l| l.starts_with(&format!("{} =", campo))).unwrap().to_string();
+            texto = texto.replace(&linea, &format!("{} = {}", campo, valor));
+            error(&texto)
+        };
+
+        assert_eq!(sol("radio_esfera", "-1.0"), "prueba.toml:19: sol.radio_esfera: debe ser un numero finito mayor que 0");
+        assert_eq!(sol("intensity", "-3.5"), "prueba.toml:22: sol.intensity: debe ser un numero finito mayor o igual que 0");
+        assert_eq!(sol("radius", "-3.0"), "prueba.toml:23: sol.radius: debe ser un numero finito mayor o igual que 0");
+    }
+
+    #[test]
+    fn rechaza_un_intervalo_no_finito() {
+        let texto = escena(Some(("intervalo = 10.0", "intervalo = inf")), "");
+        assert_eq!(error(&texto), "prueba.toml:10: cielo.intervalo: debe ser un numero finito mayor que 0");
+    }
+
+    #[test]
+    fn senala_el_material_desconocido_dentro_de_la_lista() {
+        let texto = escena(None, "\n[[cubos]]\ncenter = [0.0, 0.0, 0.0]\nsize = 1.0\nmateriales = [\n    \"piedra\",\n    \"piedra\",\n    \"pierda\",\n    \"piedra\",\n    \"piedra\",\n    \"piedra\",\n]\n");
+        assert_eq!(error(&texto), "prueba.toml:23: cubos[0].materiales: material desconocido 'pierda'");
+    }
+
+    #[test]
+    fn rechaza_tamanos_y_dimensiones_invalidas() {
+        let texto = escena(None, "\n[[cubos]]\ncenter = [0.0, 0.0, 0.0]\nsize = 0.0\nmateriales = [\"piedra\"]\n");
+        assert_eq!(error(&texto), "prueba.toml:19: cubos[0].size: debe ser un numero finito mayor que 0");
+
+        let texto = escena(None, "\n[[voxeles]]\norigen = [0.0, 0.0, 0.0]\ntamano_bloque = 1.0\ndimensiones = [4, 0, 4]\n\n[voxeles.bloques]\npiedra = [\"piedra\"]\n");
+        assert_eq!(error(&texto), "prueba.toml:20: voxeles[0].dimensiones: todas las dimensiones deben ser mayores que 0");
+    }
+}
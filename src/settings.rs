@@ -0,0 +1,84 @@
+use std::str::FromStr;
+use crate::antialiasing::{Filtro, Muestreo};
+
+// Whitted sigue solo reflexiones y refracciones perfectas; Caminos estima la iluminacion global y
+// Oclusion muestra solo la oclusion ambiental para depurarla.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModoRender {
+    Whitted,
+    Caminos,
+    Oclusion,
+}
+
+impl ModoRender {
+    pub fn siguiente(self) -> Self {
+        match self {
+            ModoRender::Whitted => ModoRender::Caminos,
+            ModoRender::Caminos => ModoRender::Oclusion,
+            ModoRender::Oclusion => ModoRender::Whitted,
+        }
+    }
+}
+
+impl FromStr for ModoRender {
+    type Err = String;
+
+    fn from_str(texto: &str) -> Result<Self, String> {
+        match texto {
+            "whitted" => Ok(ModoRender::Whitted),
+            "path" => Ok(ModoRender::Caminos),
+            "ao" => Ok(ModoRender::Oclusion),
+            otro => Err(format!("modo desconocido: {} (whitted, path o ao)", otro)),
+        }
+    }
+}
+
+pub struct RenderSettings {
+    pub shadows: bool,
+    pub shadow_samples: u32,
+    pub max_depth: u32,
+    pub threads: usize,
+    pub supersampling: u32,
+    pub muestreo: Muestreo,
+    pub filtro: Filtro,
+    pub modo: ModoRender,
+    pub muestras_camino: u32,
+    pub rebotes_max: u32,
+    pub oclusion: bool,
+    pub oclusion_muestras: u32,
+    pub oclusion_distancia: f32,
+}
+
+impl RenderSettings {
+    pub fn new() -> Self {
+        RenderSettings {
+            shadows: true,
+            shadow_samples: 1,
+            max_depth: 3,
+            threads: 0,
+            supersampling: 1,
+            muestreo: Muestreo::Estratificado,
+            filtro: Filtro::Caja,
+            modo: ModoRender::Whitted,
+            muestras_camino: 1,
+            rebotes_max: 5,
+            oclusion: false,
+            oclusion_muestras: 4,
+            oclusion_distancia: 1.0,
+        }
+    }
+
+    // Con 0 hilos se usan todos los nucleos disponibles.
+    pub fn hilos(&self) -> usize {
+        if self.threads > 0 {
+            return self.threads;
+        }
+        std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,115 @@
+use nalgebra_glm::Vec3;
+use crate::bvh::Bvh;
+use crate::light::Light;
+use crate::random::Rng;
+use crate::ray_intersect::Intersect;
+use crate::settings::RenderSettings;
+use crate::texturas::Cono;
+
+pub fn reflector(incidente: &Vec3, normal: &Vec3) -> Vec3 {
+    incidente - 2.0 * incidente.dot(normal) * normal
+}
+
+pub fn refractor(incidente: &Vec3, normal: &Vec3, eta_i: f32, eta_t: f32) -> Option<Vec3> {
+    let cos_i = -incidente.dot(normal).clamp(-1.0, 1.0);
+    let eta = eta_i / eta_t;
+    let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+
+    if k < 0.0 {
+        return None;
+    }
+
+    Some((incidente * eta + normal * (eta * cos_i - k.sqrt())).normalize())
+}
+
+pub fn fresnel_schlick(incidente: &Vec3, normal: &Vec3, eta_i: f32, eta_t: f32) -> f32 {
+    let mut cos_i = -incidente.dot(normal).clamp(-1.0, 1.0);
+
+    if eta_i > eta_t {
+        let sin_t2 = (eta_i / eta_t).powi(2) * (1.0 - cos_i * cos_i);
+        if sin_t2 > 1.0 {
+            return 1.0;
+        }
+        cos_i = (1.0 - sin_t2).sqrt();
+    }
+
+    let r0 = ((eta_i - eta_t) / (eta_i + eta_t)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos_i).powi(5)
+}
+
+const SHADOW_BIAS: f32 = 1e-4;
+
+pub fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3 {
+    let offset = intersect.normal_geometrica * SHADOW_BIAS;
+    if direction.dot(&intersect.normal_geometrica) < 0.0 {
+        intersect.point - offset
+    } else {
+        intersect.point + offset
+    }
+}
+
+pub fn cast_shadow(intersect: &Intersect, luz: &Light, objects: &Bvh, settings: &RenderSettings, rng: &mut Rng) -> f32 {
+    let muestras = settings.shadow_samples.max(1);
+    let mut bloqueadas = 0.0;
+
+    for muestra in 0..muestras {
+        let (light_dir, distancia_luz) = luz.muestra_sombra(&intersect.point, muestra, muestras, rng);
+        let shadow_origin = offset_origin(intersect, &light_dir);
+
+        // Sin materiales transparentes basta la consulta de cualquier golpe; con ellos se recorre una sola
+        // vez el rayo sumando lo que deja pasar cada superficie.
+        if objects.hay_transparentes() {
+            bloqueadas += 1.0 - transmision_sombra(&shadow_origin, &light_dir, distancia_luz, objects);
+        } else if objects.ocluido(&shadow_origin, &light_dir, distancia_luz) {
+            bloqueadas += 1.0;
+        }
+    }
+
+    bloqueadas / muestras as f32
+}
+
+// Fraccion de luz que atraviesa los objetos entre el origen y la luz: cada superficie transparente
+// (agua, vidrio) deja pasar su transparencia y una opaca corta el rayo.
+fn transmision_sombra(origen: &Vec3, direccion: &Vec3, distancia: f32, objects: &Bvh) -> f32 {
+    let mut transmision = 1.0;
+    let mut origen = *origen;
+    let mut restante = distancia;
+
+    while transmision > 0.0 {
+        let golpe = objects.intersect(&origen, direccion);
+        if !golpe.is_intersecting || golpe.distance >= restante {
+            break;
+        }
+
+        transmision *= golpe.material.transparencia;
+        restante -= golpe.distance + SHADOW_BIAS;
+        origen = golpe.point + direccion * SHADOW_BIAS;
+    }
+
+    transmision
+}
+
+// Calcula la huella del pixel, aplica los mapas de normales y voltea las normales si el rayo viene
+// desde adentro del objeto. El cono ya debe estar propagado hasta el punto de impacto.
+pub fn preparar_golpe(intersect: &mut Intersect, ray_direction: &Vec3, cono: &Cono) -> (f32, bool) {
+    // Huella del pixel sobre la superficie en unidades de UV; las caras vistas de lado la estiran.
+    let coseno = ray_direction.dot(&intersect.normal_geometrica).abs().max(0.05);
+    let huella = cono.ancho / (coseno * intersect.tamano_uv);
+
+    intersect.normal = intersect.material.normal_sombreado(
+        &intersect.normal_geometrica,
+        &intersect.tangente,
+        &intersect.bitangente,
+        intersect.u,
+        intersect.v,
+        huella,
+    );
+
+    let desde_adentro = ray_direction.dot(&intersect.normal_geometrica) > 0.0;
+    if desde_adentro {
+        intersect.normal = -intersect.normal;
+        intersect.normal_geometrica = -intersect.normal_geometrica;
+    }
+
+    (huella, desde_adentro)
+}
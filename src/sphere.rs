@@ -1,7 +1,10 @@
 use nalgebra_glm::{Vec3, dot};
-use crate::ray_intersect::{RayIntersect, Intersect};
+use crate::ray_intersect::{marco_tangente, RayIntersect, Intersect};
 use crate::material::Material;
+use crate::light::Light;
+use crate::aabb::Aabb;
 use std::any::Any;
+use std::f32::consts::PI;
 
 #[derive(Clone)]
 pub struct Sphere {
@@ -21,19 +24,37 @@ impl RayIntersect for Sphere {
         let discriminant = b * b - 4.0 * a * c;
 
         if discriminant > 0.0 {
-            let t = (-b - discriminant.sqrt()) / (2.0 * a);
+            let raiz = discriminant.sqrt();
+            let t_cerca = (-b - raiz) / (2.0 * a);
+            let t = if t_cerca > 0.0 { t_cerca } else { (-b + raiz) / (2.0 * a) };
             if t > 0.0 {
                 let point = ray_origin + ray_direction * t;
                 let normal = (point - self.center).normalize();
                 let distance = t;
-
-                return Intersect::new(point, normal, distance, self.material.clone(), 0.0, 0.0); 
+                let (u, v) = self.get_uv(&point);
+
+                // La tangente sigue a u alrededor del eje y; en los polos no esta definida y se usa cualquiera.
+                let horizontal = Vec3::new(normal.z, 0.0, -normal.x);
+                let (tangente, bitangente) = if horizontal.magnitude() > 1e-4 {
+                    let tangente = horizontal.normalize();
+                    (tangente, normal.cross(&tangente))
+                } else {
+                    marco_tangente(&normal)
+                };
+
+                return Intersect {
+                    tamano_uv: 2.0 * PI * self.radius,
+                    tangente,
+                    bitangente,
+                    ..Intersect::new(point, normal, distance, self.material.clone(), u, v)
+                };
             }
         }
 
         Intersect::empty()
     }
 
+    // Visto desde afuera u avanza hacia la derecha y v hacia el polo norte, como en las caras de los cubos.
     fn get_uv(&self, point: &Vec3) -> (f32, f32) {
 
         let r = (point - self.center).normalize();
@@ -42,15 +63,28 @@ impl RayIntersect for Sphere {
         let theta = r.z.atan2(r.x);
 
 
-        let phi = r.y.asin();
+        let phi = r.y.clamp(-1.0, 1.0).asin();
 
-        let u = 0.5 + theta / (2.0 * std::f32::consts::PI);
-        let v = 0.5 - phi / std::f32::consts::PI;
+        let u = 0.5 - theta / (2.0 * PI);
+        let v = 0.5 + phi / PI;
 
         (u, v)
     }
 
+    fn bounding_box(&self) -> Aabb {
+        let radio = Vec3::repeat(self.radius);
+        Aabb::new(self.center - radio, self.center + radio)
+    }
+
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn transparente(&self) -> bool {
+        self.material.transparencia > 0.0
+    }
+
+    fn luces_emisivas(&self) -> Vec<Light> {
+        self.material.luz_emisiva(self.center, self.radius).into_iter().collect()
+    }
 }
\ No newline at end of file
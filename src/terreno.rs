@@ -0,0 +1,228 @@
+use nalgebra_glm::Vec3;
+use image::GrayImage;
+use crate::cube::Cube;
+use crate::material::Material;
+use crate::random::Rng;
+use crate::voxel_grid::VoxelGrid;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TipoBloque {
+    Grama,
+    Tierra,
+    Piedra,
+    Agua,
+}
+
+pub enum FuenteAltura {
+    Mapa(GrayImage),
+    Ruido { semilla: u64, escala: f32, octavas: u32 },
+}
+
+pub struct BloquesTerreno {
+    pub grama: [Material; 6],
+    pub tierra: [Material; 6],
+    pub piedra: [Material; 6],
+    pub agua: [Material; 6],
+}
+
+impl BloquesTerreno {
+    fn materiales(&self, tipo: TipoBloque) -> &[Material; 6] {
+        match tipo {
+            TipoBloque::Grama => &self.grama,
+            TipoBloque::Tierra => &self.tierra,
+            TipoBloque::Piedra => &self.piedra,
+            TipoBloque::Agua => &self.agua,
+        }
+    }
+}
+
+// Genera columnas de bloques: grama arriba, tierra debajo, piedra en lo profundo y agua hasta el nivel del mar.
+pub struct GeneradorTerreno {
+    pub dimensiones: [usize; 3],
+    pub nivel_mar: usize,
+    pub capas_tierra: usize,
+    pub fuente: FuenteAltura,
+}
+
+impl GeneradorTerreno {
+    pub fn alturas(&self) -> Vec<usize> {
+        let [ancho, alto, profundo] = self.dimensiones;
+        let maximo = alto.saturating_sub(1) as f32;
+        let mut alturas = Vec::with_capacity(ancho * profundo);
+
+        match &self.fuente {
+            FuenteAltura::Mapa(imagen) => {
+                for z in 0..profundo {
+                    for x in 0..ancho {
+                        let px = (x * imagen.width() as usize / ancho) as u32;
+                        let pz = (z * imagen.height() as usize / profundo) as u32;
+                        let gris = imagen.get_pixel(px, pz)[0] as f32 / 255.0;
+                        alturas.push((gris * maximo).round() as usize);
+                    }
+                }
+            }
+            FuenteAltura::Ruido { semilla, escala, octavas } => {
+                let perlin = Perlin::new(*semilla);
+                for z in 0..profundo {
+                    for x in 0..ancho {
+                        let n = perlin.fbm(x as f32 * escala, z as f32 * escala, *octavas);
+                        let t = (n * 0.5 + 0.5).clamp(0.0, 1.0);
+                        alturas.push((t * maximo).round() as usize);
+                    }
+                }
+            }
+        }
+
+        alturas
+    }
+
+    pub fn tipo_bloque(&self, y: usize, altura: usize) -> Option<TipoBloque> {
+        if y > altura {
+            return if y <= self.nivel_mar { Some(TipoBloque::Agua) } else { None };
+        }
+
+        if y == altura && altura >= self.nivel_mar {
+            Some(TipoBloque::Grama)
+        } else if y + self.capas_tierra >= altura {
+            Some(TipoBloque::Tierra)
+        } else {
+            Some(TipoBloque::Piedra)
+        }
+    }
+
+    pub fn generar_voxeles(&self, origen: Vec3, tamano_bloque: f32, bloques: &BloquesTerreno) -> VoxelGrid {
+        let [ancho, alto, profundo] = self.dimensiones;
+        let mut grid = VoxelGrid::new(origen, tamano_bloque, self.dimensiones);
+
+        let ids = [
+            grid.registrar_bloque(bloques.grama.clone()),
+            grid.registrar_bloque(bloques.tierra.clone()),
+            grid.registrar_bloque(bloques.piedra.clone()),
+            grid.registrar_bloque(bloques.agua.clone()),
+        ];
+
+        let alturas = self.alturas();
+        for z in 0..profundo {
+            for x in 0..ancho {
+                let altura = alturas[z * ancho + x];
+                for y in 0..alto {
+                    if let Some(tipo) = self.tipo_bloque(y, altura) {
+                        grid.set_bloque(x, y, z, ids[tipo as usize]);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    // Como cubos sueltos solo se emiten los bloques con alguna cara expuesta al aire.
+    pub fn generar_cubos(&self, origen: Vec3, tamano_bloque: f32, bloques: &BloquesTerreno) -> Vec<Cube> {
+        let [ancho, alto, profundo] = self.dimensiones;
+        let alturas = self.alturas();
+        let tipo_en = |x: i64, y: i64, z: i64| {
+            if x < 0 || y < 0 || z < 0 || x >= ancho as i64 || y >= alto as i64 || z >= profundo as i64 {
+                return None;
+            }
+            self.tipo_bloque(y as usize, alturas[z as usize * ancho + x as usize])
+        };
+
+        let mut cubos = Vec::new();
+        for z in 0..profundo as i64 {
+            for x in 0..ancho as i64 {
+                for y in 0..alto as i64 {
+                    let Some(tipo) = tipo_en(x, y, z) else {
+                        continue;
+                    };
+
+                    let vecinos = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+                    let expuesto = vecinos.iter().any(|(dx, dy, dz)| tipo_en(x + dx, y + dy, z + dz) != Some(tipo));
+                    if !expuesto {
+                        continue;
+                    }
+
+                    let center = origen + Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5) * tamano_bloque;
+                    cubos.push(Cube::new(center, tamano_bloque, bloques.materiales(tipo).clone()));
+                }
+            }
+        }
+
+        cubos
+    }
+}
+
+// Ruido de Perlin 2D con tabla de permutacion sembrada.
+struct Perlin {
+    permutacion: [u8; 512],
+}
+
+impl Perlin {
+    fn new(semilla: u64) -> Self {
+        let mut rng = Rng::new(semilla);
+        let mut tabla: Vec<u8> = (0..=255).collect();
+        for i in (1..256).rev() {
+            let j = (rng.next_u32() as usize) % (i + 1);
+            tabla.swap(i, j);
+        }
+
+        let mut permutacion = [0u8; 512];
+        for i in 0..512 {
+            permutacion[i] = tabla[i & 255];
+        }
+        Perlin { permutacion }
+    }
+
+    fn gradiente(hash: u8, x: f32, y: f32) -> f32 {
+        match hash & 7 {
+            0 => x + y,
+            1 => -x + y,
+            2 => x - y,
+            3 => -x - y,
+            4 => x,
+            5 => -x,
+            6 => y,
+            _ => -y,
+        }
+    }
+
+    fn ruido(&self, x: f32, y: f32) -> f32 {
+        let xi = (x.floor() as i64 & 255) as usize;
+        let yi = (y.floor() as i64 & 255) as usize;
+        let xf = x - x.floor();
+        let yf = y - y.floor();
+
+        let suavizar = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let u = suavizar(xf);
+        let v = suavizar(yf);
+
+        let p = &self.permutacion;
+        let aa = p[p[xi] as usize + yi];
+        let ab = p[p[xi] as usize + yi + 1];
+        let ba = p[p[xi + 1] as usize + yi];
+        let bb = p[p[xi + 1] as usize + yi + 1];
+
+        let x1 = lerp(Self::gradiente(aa, xf, yf), Self::gradiente(ba, xf - 1.0, yf), u);
+        let x2 = lerp(Self::gradiente(ab, xf, yf - 1.0), Self::gradiente(bb, xf - 1.0, yf - 1.0), u);
+        lerp(x1, x2, v)
+    }
+
+    fn fbm(&self, x: f32, y: f32, octavas: u32) -> f32 {
+        let mut total = 0.0;
+        let mut amplitud = 1.0;
+        let mut frecuencia = 1.0;
+        let mut normalizacion = 0.0;
+
+        for _ in 0..octavas.max(1) {
+            total += self.ruido(x * frecuencia, y * frecuencia) * amplitud;
+            normalizacion += amplitud;
+            amplitud *= 0.5;
+            frecuencia *= 2.0;
+        }
+
+        total / normalizacion
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
@@ -1,9 +1,107 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use image::imageops::{self, FilterType};
 use image::RgbaImage;
+use crate::color::Color;
+use crate::sampler::Envoltura;
+
+// Cono de rayo: ancho de la huella del pixel en el origen y cuanto crece por unidad de distancia.
+#[derive(Debug, Clone, Copy)]
+pub struct Cono {
+    pub ancho: f32,
+    pub apertura: f32,
+}
+
+impl Cono {
+    pub fn new(apertura: f32) -> Self {
+        Cono { ancho: 0.0, apertura }
+    }
+
+    pub fn ancho_en(&self, distancia: f32) -> f32 {
+        self.ancho + self.apertura * distancia
+    }
+
+    pub fn propagar(&self, distancia: f32) -> Cono {
+        Cono { ancho: self.ancho_en(distancia), apertura: self.apertura }
+    }
+}
+
+// Imagen con su cadena de mipmaps; el nivel 0 es la original y cada nivel mide la mitad del anterior.
+#[derive(Debug, PartialEq)]
+pub struct Textura {
+    niveles: Vec<RgbaImage>,
+}
+
+impl Textura {
+    pub fn new(imagen: RgbaImage) -> Self {
+        let mut niveles = vec![imagen];
+        loop {
+            let anterior = &niveles[niveles.len() - 1];
+            if anterior.width() == 1 && anterior.height() == 1 {
+                break;
+            }
+
+            let ancho = (anterior.width() / 2).max(1);
+            let alto = (anterior.height() / 2).max(1);
+            let siguiente = imageops::resize(anterior, ancho, alto, FilterType::Triangle);
+            niveles.push(siguiente);
+        }
+
+        Textura { niveles }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.niveles[0].width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.niveles[0].height()
+    }
+
+    pub fn cantidad_niveles(&self) -> usize {
+        self.niveles.len()
+    }
+
+    pub fn cercano(&self, nivel: usize, u: f32, v: f32, envoltura: Envoltura) -> Color {
+        let imagen = &self.niveles[nivel];
+        let x = envoltura.indice((u * imagen.width() as f32).floor() as i64, imagen.width());
+        let y = envoltura.indice((v * imagen.height() as f32).floor() as i64, imagen.height());
+
+        let pixel = imagen.get_pixel(x, y);
+        Color::new(pixel[0], pixel[1], pixel[2])
+    }
+
+    pub fn bilineal(&self, nivel: usize, u: f32, v: f32, envoltura: Envoltura) -> Color {
+        let imagen = &self.niveles[nivel];
+        let s = u * imagen.width() as f32 - 0.5;
+        let t = v * imagen.height() as f32 - 0.5;
+        let fx = s - s.floor();
+        let fy = t - t.floor();
+
+        let x0 = envoltura.indice(s.floor() as i64, imagen.width());
+        let x1 = envoltura.indice(s.floor() as i64 + 1, imagen.width());
+        let y0 = envoltura.indice(t.floor() as i64, imagen.height());
+        let y1 = envoltura.indice(t.floor() as i64 + 1, imagen.height());
+
+        let mut canales = [0.0f32; 3];
+        for (x, y, peso) in [
+            (x0, y0, (1.0 - fx) * (1.0 - fy)),
+            (x1, y0, fx * (1.0 - fy)),
+            (x0, y1, (1.0 - fx) * fy),
+            (x1, y1, fx * fy),
+        ] {
+            let pixel = imagen.get_pixel(x, y);
+            for (canal, valor) in canales.iter_mut().zip(pixel.0.iter()) {
+                *canal += *valor as f32 * peso;
+            }
+        }
+
+        Color::new(canales[0].round() as u8, canales[1].round() as u8, canales[2].round() as u8)
+    }
+}
 
 pub struct TextureManager {
-    textures: HashMap<String, Arc<RgbaImage>>,
+    textures: HashMap<String, Arc<Textura>>,
 }
 
 impl TextureManager {
@@ -14,11 +112,11 @@ impl TextureManager {
     }
 
     pub fn cargar_textura(&mut self, name: &str, image: RgbaImage) {
-        let texture = Arc::new(image);
+        let texture = Arc::new(Textura::new(image));
         self.textures.insert(name.to_string(), texture);
     }
 
-    pub fn get_textura(&self, name: &str) -> Option<Arc<RgbaImage>> {
+    pub fn get_textura(&self, name: &str) -> Option<Arc<Textura>> {
         self.textures.get(name).cloned()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,176 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::material::Modelo;
+use crate::pbr::{self, color_a_vec3, vec3_a_color};
+use crate::random::Rng;
+use crate::ray_intersect::{marco_tangente, Intersect};
+use crate::scene::Scene;
+use crate::settings::RenderSettings;
+use crate::sombreado::{cast_shadow, fresnel_schlick, offset_origin, preparar_golpe, reflector, refractor};
+use crate::texturas::Cono;
+
+// Direccion en el hemisferio de la normal con densidad proporcional al coseno (cos / pi).
+pub fn direccion_coseno(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    let (tangente, bitangente) = marco_tangente(normal);
+    let r = rng.next_f32().sqrt();
+    let theta = 2.0 * PI * rng.next_f32();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+
+    (tangente * (r * theta.cos()) + bitangente * (r * theta.sin()) + normal * z).normalize()
+}
+
+// Promedia varios caminos por rayo de camara; el resultado converge a la iluminacion global.
+pub fn trazar(origen: &Vec3, direccion: &Vec3, cono: Cono, scene: &Scene, settings: &RenderSettings, rng: &mut Rng) -> Color {
+    let muestras = settings.muestras_camino.max(1);
+    let mut suma = Vec3::zeros();
+    for _ in 0..muestras {
+        suma += camino(origen, direccion, cono, scene, settings, rng);
+    }
+
+    // Si algun canal se pasa de 1 se escala todo el color, asi lo muy iluminado conserva su tono en vez
+    // de volverse blanco.
+    let promedio = suma / muestras as f32;
+    let maximo = promedio.max();
+    if maximo > 1.0 {
+        return vec3_a_color(&(promedio / maximo));
+    }
+    vec3_a_color(&promedio)
+}
+
+fn camino(origen: &Vec3, direccion: &Vec3, cono: Cono, scene: &Scene, settings: &RenderSettings, rng: &mut Rng) -> Vec3 {
+    let mut radiancia = Vec3::zeros();
+    let mut peso = Vec3::repeat(1.0);
+    let mut origen = *origen;
+    let mut direccion = *direccion;
+    let mut cono = cono;
+    // La emision se cuenta al verla directamente o tras un rebote especular; en los rebotes difusos ya
+    // la aporto la estimacion hacia las luces (los objetos emisivos estan registrados como luces).
+    let mut especular = true;
+
+    for rebote in 0..=settings.rebotes_max {
+        let mut intersect = scene.objects.intersect(&origen, &direccion);
+        if !intersect.is_intersecting {
+            radiancia += peso.component_mul(&color_a_vec3(scene.fondo(&direccion)));
+            break;
+        }
+
+        cono = cono.propagar(intersect.distance);
+        let (huella, desde_adentro) = preparar_golpe(&mut intersect, &direccion, &cono);
+        let material = &intersect.material;
+
+        if especular {
+            if let Some(emision) = material.emision() {
+                radiancia += peso.component_mul(&color_a_vec3(emision));
+            }
+        }
+
+        let base = color_a_vec3(material.get_diffuse_color(intersect.u, intersect.v, huella));
+        let vista = -direccion;
+
+        // Se elige un solo evento con la misma proporcion con la que cast_ray mezcla los colores.
+        let evento = rng.next_f32();
+        if evento < material.reflectividad {
+            direccion = reflector(&direccion, &intersect.normal).normalize();
+            especular = true;
+        } else if evento < material.reflectividad + material.transparencia {
+            let (eta_i, eta_t) = if desde_adentro {
+                (material.indice_refraccion, 1.0)
+            } else {
+                (1.0, material.indice_refraccion)
+            };
+
+            let fresnel = fresnel_schlick(&direccion, &intersect.normal, eta_i, eta_t);
+            direccion = match refractor(&direccion, &intersect.normal, eta_i, eta_t) {
+                Some(refractada) if rng.next_f32() >= fresnel => refractada,
+                _ => reflector(&direccion, &intersect.normal).normalize(),
+            };
+            especular = true;
+        } else {
+            radiancia += peso.component_mul(&luz_directa(&intersect, &vista, &base, huella, scene, settings, rng));
+
+            let nueva = direccion_coseno(&intersect.normal, rng);
+            // Con relieve el hemisferio de la normal de sombreado puede meterse bajo la superficie.
+            if nueva.dot(&intersect.normal_geometrica) <= 0.0 {
+                break;
+            }
+            let factor = match material.modelo {
+                // Lambert con muestreo por coseno: BRDF * cos / pdf = color base, pesado por albedo[0] como en cast_ray.
+                Modelo::Phong => base * material.propiedades(intersect.u, intersect.v, huella).1[0],
+                Modelo::Pbr { metalico, rugosidad } => {
+                    let coseno = intersect.normal.dot(&nueva);
+                    if coseno <= 0.0 {
+                        break;
+                    }
+                    let rugosidad = material.rugosidad(rugosidad, intersect.u, intersect.v, huella);
+                    pbr::cook_torrance(&intersect.normal, &vista, &nueva, &base, metalico, rugosidad) * PI / coseno
+                }
+            };
+
+            peso = peso.component_mul(&factor);
+            direccion = nueva;
+            especular = false;
+        }
+
+        origen = offset_origin(&intersect, &direccion);
+
+        // Ruleta rusa: los caminos que ya aportan poco terminan antes, compensando a los que siguen.
+        if rebote >= 2 {
+            let supervivencia = peso.max().clamp(0.05, 0.95);
+            if rng.next_f32() > supervivencia {
+                break;
+            }
+            peso /= supervivencia;
+        }
+    }
+
+    radiancia
+}
+
+// Estimacion directa hacia cada luz, ya multiplicada por la BRDF y el coseno.
+fn luz_directa(intersect: &Intersect, vista: &Vec3, base: &Vec3, huella: f32, scene: &Scene, settings: &RenderSettings, rng: &mut Rng) -> Vec3 {
+    let material = &intersect.material;
+    let (exponente_especular, albedo) = material.propiedades(intersect.u, intersect.v, huella);
+    let mut total = Vec3::zeros();
+
+    for luz in &scene.luces {
+        let atenuacion = luz.atenuacion(&intersect.point);
+        if atenuacion <= 0.0 {
+            continue;
+        }
+
+        let sombra = if settings.shadows {
+            cast_shadow(intersect, luz, &scene.objects, settings, rng)
+        } else {
+            0.0
+        };
+        let intensidad = luz.intensity * atenuacion * (1.0 - sombra);
+        if intensidad <= 0.0 {
+            continue;
+        }
+
+        let light_dir = luz.direccion_hacia(&intersect.point);
+        let coseno = intersect.normal.dot(&light_dir);
+        if coseno <= 0.0 {
+            continue;
+        }
+
+        // Igual que en cast_ray, la intensidad es la irradiancia de frente.
+        let radiancia = color_a_vec3(luz.color) * intensidad * PI;
+        let brdf_coseno = match material.modelo {
+            Modelo::Phong => {
+                let reflejada = reflector(&-light_dir, &intersect.normal);
+                let spec = vista.dot(&reflejada).max(0.0).powf(exponente_especular);
+                base * albedo[0] * coseno / PI + Vec3::repeat(albedo[1] * spec / PI)
+            }
+            Modelo::Pbr { metalico, rugosidad } => {
+                let rugosidad = material.rugosidad(rugosidad, intersect.u, intersect.v, huella);
+                pbr::cook_torrance(&intersect.normal, vista, &light_dir, base, metalico, rugosidad)
+            }
+        };
+
+        total += brdf_coseno.component_mul(&radiancia);
+    }
+
+    total
+}
@@ -0,0 +1,415 @@
+use nalgebra_glm::Vec3;
+use crate::aabb::Aabb;
+use crate::sampler::{tangentes_cara, uv_cara};
+use crate::light::Light;
+use crate::material::Material;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use std::any::Any;
+
+pub const AIRE: u16 = 0;
+
+#[derive(Clone)]
+pub struct VoxelGrid {
+    pub origen: Vec3,
+    pub tamano_bloque: f32,
+    pub dimensiones: [usize; 3],
+    bloques: Vec<u16>,
+    paleta: Vec<[Material; 6]>,
+}
+
+impl VoxelGrid {
+    pub fn new(origen: Vec3, tamano_bloque: f32, dimensiones: [usize; 3]) -> Self {
+        VoxelGrid {
+            origen,
+            tamano_bloque,
+            dimensiones,
+            bloques: vec![AIRE; dimensiones[0] * dimensiones[1] * dimensiones[2]],
+            paleta: Vec::new(),
+        }
+    }
+
+    // Devuelve el id con el que se colocan bloques de este tipo; el 0 queda reservado para el aire.
+    pub fn registrar_bloque(&mut self, materials: [Material; 6]) -> u16 {
+        self.paleta.push(materials);
+        self.paleta.len() as u16
+    }
+
+    fn indice(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.dimensiones[1] + y) * self.dimensiones[0] + x
+    }
+
+    pub fn get_bloque(&self, x: usize, y: usize, z: usize) -> u16 {
+        if x >= self.dimensiones[0] || y >= self.dimensiones[1] || z >= self.dimensiones[2] {
+            return AIRE;
+        }
+        self.bloques[self.indice(x, y, z)]
+    }
+
+    pub fn set_bloque(&mut self, x: usize, y: usize, z: usize, id: u16) {
+        if x < self.dimensiones[0] && y < self.dimensiones[1] && z < self.dimensiones[2] {
+            let indice = self.indice(x, y, z);
+            self.bloques[indice] = id;
+        }
+    }
+
+    pub fn contiene(&self, posicion: &Vec3) -> bool {
+        let local = (posicion - self.origen) / self.tamano_bloque;
+        (0..3).all(|i| local[i] >= 0.0 && (local[i] as usize) < self.dimensiones[i])
+    }
+
+    pub fn set_bloque_en(&mut self, posicion: &Vec3, id: u16) {
+        let local = (posicion - self.origen) / self.tamano_bloque;
+        if local.x < 0.0 || local.y < 0.0 || local.z < 0.0 {
+            return;
+        }
+        self.set_bloque(local.x as usize, local.y as usize, local.z as usize, id);
+    }
+
+    fn materiales(&self, id: u16) -> &[Material; 6] {
+        &self.paleta[id as usize - 1]
+    }
+
+    fn celda_centro(&self, x: usize, y: usize, z: usize) -> Vec3 {
+        self.origen + Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5) * self.tamano_bloque
+    }
+
+    fn uv_en_cara(&self, punto: &Vec3, cara: usize) -> (f32, f32) {
+        let local = (punto - self.origen) / self.tamano_bloque;
+        let fraccion = local.map(|c| c - c.floor());
+        uv_cara(&fraccion, cara)
+    }
+
+    fn golpe(&self, ray_origin: &Vec3, ray_direction: &Vec3, t: f32, eje: usize, signo: f32, id: u16) -> Intersect {
+        let punto = ray_origin + ray_direction * t;
+        let mut normal = Vec3::zeros();
+        normal[eje] = signo;
+
+        let cara = eje * 2 + if signo > 0.0 { 1 } else { 0 };
+        let (u, v) = self.uv_en_cara(&punto, cara);
+
+        let (tangente, bitangente) = tangentes_cara(cara);
+        Intersect {
+            tamano_uv: self.tamano_bloque,
+            tangente,
+            bitangente,
+            ..Intersect::new(punto, normal, t, self.materiales(id)[cara].clone(), u, v)
+        }
+    }
+}
+
+impl RayIntersect for VoxelGrid {
+    // Recorrido 3D-DDA de Amanatides y Woo: se avanza celda por celda sobre el eje cuyo borde llega primero.
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        // Sin direccion el recorrido nunca avanzaria de celda.
+        if !ray_direction.iter().all(|c| c.is_finite()) || ray_direction.iter().all(|&c| c == 0.0) {
+            return Intersect::empty();
+        }
+
+        let caja = self.bounding_box();
+
+        let mut t_entrada = 0.0f32;
+        let mut t_salida = f32::INFINITY;
+        let mut eje_entrada = None;
+        for i in 0..3 {
+            let inv = 1.0 / ray_direction[i];
+            let t0 = (caja.min[i] - ray_origin[i]) * inv;
+            let t1 = (caja.max[i] - ray_origin[i]) * inv;
+            let (cerca, lejos) = if t0 <= t1 { (t0, t1) } else { (t1, t0) };
+
+            if cerca > t_entrada {
+                t_entrada = cerca;
+                eje_entrada = Some(i);
+            }
+            t_salida = t_salida.min(lejos);
+        }
+
+        if t_entrada > t_salida {
+            return Intersect::empty();
+        }
+
+        let inicio = ray_origin + ray_direction * t_entrada;
+        let local = (inicio - self.origen) / self.tamano_bloque;
+
+        let mut celda = [0i64; 3];
+        let mut paso = [0i64; 3];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+
+        for i in 0..3 {
+            celda[i] = (local[i].floor() as i64).clamp(0, self.dimensiones[i] as i64 - 1);
+
+            if ray_direction[i] > 0.0 {
+                paso[i] = 1;
+                let borde = self.origen[i] + (celda[i] + 1) as f32 * self.tamano_bloque;
+                t_max[i] = (borde - ray_origin[i]) / ray_direction[i];
+                t_delta[i] = self.tamano_bloque / ray_direction[i];
+            } else if ray_direction[i] < 0.0 {
+                paso[i] = -1;
+                let borde = self.origen[i] + celda[i] as f32 * self.tamano_bloque;
+                t_max[i] = (borde - ray_origin[i]) / ray_direction[i];
+                t_delta[i] = -self.tamano_bloque / ray_direction[i];
+            }
+        }
+
+        // Si el rayo nace dentro de un bloque (refraccion) se busca la cara por la que sale de ese material.
+        let id_inicial = if eje_entrada.is_none() {
+            self.get_bloque(celda[0] as usize, celda[1] as usize, celda[2] as usize)
+        } else {
+            AIRE
+        };
+
+        let mut t_actual = t_entrada;
+        let mut eje_actual = eje_entrada.unwrap_or(0);
+
+        // Un rayo recto no cruza mas celdas que la suma de las dimensiones; el limite evita quedarse en un
+        // ciclo si el redondeo deja una celda sin avanzar.
+        let max_pasos = self.dimensiones.iter().sum::<usize>() + 1;
+        for _ in 0..max_pasos {
+            let id = self.get_bloque(celda[0] as usize, celda[1] as usize, celda[2] as usize);
+
+            if id != id_inicial {
+                return if id != AIRE {
+                    self.golpe(ray_origin, ray_direction, t_actual, eje_actual, -(paso[eje_actual] as f32), id)
+                } else {
+                    self.golpe(ray_origin, ray_direction, t_actual, eje_actual, paso[eje_actual] as f32, id_inicial)
+                };
+            }
+
+            let eje = if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] { 0 } else { 2 }
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            };
+
+            celda[eje] += paso[eje];
+            let fuera = celda[eje] < 0 || celda[eje] >= self.dimensiones[eje] as i64;
+
+            if t_max[eje] > t_salida || fuera {
+                if id_inicial != AIRE {
+                    return self.golpe(ray_origin, ray_direction, t_max[eje].min(t_salida), eje, paso[eje] as f32, id_inicial);
+                }
+                return Intersect::empty();
+            }
+
+            t_actual = t_max[eje];
+            eje_actual = eje;
+            t_max[eje] += t_delta[eje];
+        }
+
+        Intersect::empty()
+    }
+
+    fn get_uv(&self, point: &Vec3) -> (f32, f32) {
+        let local = (point - self.origen) / self.tamano_bloque;
+        let distancia_borde = |c: f32| (c - c.round()).abs();
+
+        let mut eje = 0;
+        for i in 1..3 {
+            if distancia_borde(local[i]) < distancia_borde(local[eje]) {
+                eje = i;
+            }
+        }
+
+        // En el borde entre dos celdas la cara es la del bloque solido: +eje si esta abajo, -eje si esta arriba.
+        let mut celda = [0i64; 3];
+        for i in 0..3 {
+            celda[i] = local[i].floor() as i64;
+        }
+        celda[eje] = local[eje].round() as i64 - 1;
+        let abajo_solido = celda.iter().all(|&c| c >= 0)
+            && self.get_bloque(celda[0] as usize, celda[1] as usize, celda[2] as usize) != AIRE;
+
+        let cara = eje * 2 + if abajo_solido { 1 } else { 0 };
+        self.uv_en_cara(point, cara)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extension = Vec3::new(
+            self.dimensiones[0] as f32,
+            self.dimensiones[1] as f32,
+            self.dimensiones[2] as f32,
+        ) * self.tamano_bloque;
+
+        Aabb::new(self.origen, self.origen + extension)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn transparente(&self) -> bool {
+        self.paleta.iter().flatten().any(|material| material.transparencia > 0.0)
+    }
+
+    // Cada region conexa de bloques emisivos iguales es una sola luz puesta sobre las caras que dan al aire;
+    // una luz por bloque volveria carisimas las sombras de un campo de lava. Una region enterrada no ilumina.
+    fn luces_emisivas(&self) -> Vec<Light> {
+        let emisivos: Vec<bool> = self.paleta
+            .iter()
+            .map(|materiales| materiales.iter().any(|m| m.emisividad_color.is_some()))
+            .collect();
+
+        if !emisivos.iter().any(|&e| e) {
+            return Vec::new();
+        }
+
+        let mitad = self.tamano_bloque / 2.0;
+        let mut visitado = vec![false; self.bloques.len()];
+        let mut luces = Vec::new();
+
+        for z in 0..self.dimensiones[2] {
+            for y in 0..self.dimensiones[1] {
+                for x in 0..self.dimensiones[0] {
+                    let id = self.bloques[self.indice(x, y, z)];
+                    if id == AIRE || !emisivos[id as usize - 1] || visitado[self.indice(x, y, z)] {
+                        continue;
+                    }
+
+                    let mut caras = Vec::new();
+                    let mut pendientes = vec![[x, y, z]];
+                    visitado[self.indice(x, y, z)] = true;
+                    while let Some([cx, cy, cz]) = pendientes.pop() {
+                        let centro = self.celda_centro(cx, cy, cz);
+
+                        let vecinos = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+                        for (dx, dy, dz) in vecinos {
+                            let normal = Vec3::new(dx as f32, dy as f32, dz as f32);
+                            let (nx, ny, nz) = (cx as i64 + dx, cy as i64 + dy, cz as i64 + dz);
+                            // Las caras en el borde de la cuadricula (como el fondo del diorama) no cuentan:
+                            // ahi no hay bloques que iluminar y tirarian la luz hacia el centro del charco.
+                            if nx < 0 || ny < 0 || nz < 0 {
+                                continue;
+                            }
+                            let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+                            if nx >= self.dimensiones[0] || ny >= self.dimensiones[1] || nz >= self.dimensiones[2] {
+                                continue;
+                            }
+
+                            let vecino = self.get_bloque(nx, ny, nz);
+                            if vecino == AIRE {
+                                caras.push((centro + normal * mitad, normal));
+                            } else if vecino == id && !visitado[self.indice(nx, ny, nz)] {
+                                visitado[self.indice(nx, ny, nz)] = true;
+                                pendientes.push([nx, ny, nz]);
+                            }
+                        }
+                    }
+
+                    if let Some(luz) = self.materiales(id).iter().find_map(|m| m.luz_sobre_caras(&caras, self.tamano_bloque)) {
+                        luces.push(luz);
+                    }
+                }
+            }
+        }
+
+        luces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::random::Rng;
+
+    // Cuadricula al azar y los mismos bloques como cubos sueltos, para comparar el DDA contra la
+    // interseccion de cada cubo.
+    fn escena() -> (VoxelGrid, Vec<Cube>) {
+        let material = Material::new(Color::new(200, 200, 200), 1.0, [1.0, 0.0], 0.0, 0.0, 1.0, None, None);
+        let mut grid = VoxelGrid::new(Vec3::new(-2.0, -1.0, -3.0), 0.5, [8, 6, 7]);
+        let id = grid.registrar_bloque(std::array::from_fn(|_| material.clone()));
+
+        let mut rng = Rng::new(17);
+        let mut cubos = Vec::new();
+        for z in 0..7 {
+            for y in 0..6 {
+                for x in 0..8 {
+                    if rng.next_f32() < 0.3 {
+                        grid.set_bloque(x, y, z, id);
+                        cubos.push(Cube::new(grid.celda_centro(x, y, z), 0.5, std::array::from_fn(|_| material.clone())));
+                    }
+                }
+            }
+        }
+
+        (grid, cubos)
+    }
+
+    fn mas_cercano(cubos: &[Cube], origen: &Vec3, direccion: &Vec3) -> Intersect {
+        let mut intersect = Intersect::empty();
+        for cubo in cubos {
+            let tmp = cubo.ray_intersect(origen, direccion);
+            if tmp.is_intersecting && (!intersect.is_intersecting || tmp.distance < intersect.distance) {
+                intersect = tmp;
+            }
+        }
+        intersect
+    }
+
+    fn comparar(grid: &VoxelGrid, cubos: &[Cube], origen: &Vec3, direccion: &Vec3) {
+        let esperado = mas_cercano(cubos, origen, direccion);
+        let obtenido = grid.ray_intersect(origen, direccion);
+
+        assert_eq!(obtenido.is_intersecting, esperado.is_intersecting, "rayo {:?} {:?}", origen, direccion);
+        if esperado.is_intersecting {
+            assert!((obtenido.distance - esperado.distance).abs() < 1e-4, "rayo {:?} {:?}", origen, direccion);
+            assert!((obtenido.point - esperado.point).magnitude() < 1e-3, "rayo {:?} {:?}", origen, direccion);
+        }
+    }
+
+    // Origenes al azar alrededor de la cuadricula, sin empezar dentro de un bloque (ahi el DDA busca la
+    // salida del material y un cubo suelto la de si mismo).
+    fn origenes(grid: &VoxelGrid, rng: &mut Rng, cantidad: usize) -> Vec<Vec3> {
+        let mut origenes = Vec::new();
+        while origenes.len() < cantidad {
+            let origen = Vec3::new(rng.next_f32() * 9.0 - 4.5, rng.next_f32() * 8.0 - 3.0, rng.next_f32() * 9.0 - 6.0);
+            let local = (origen - grid.origen) / grid.tamano_bloque;
+            let adentro = grid.contiene(&origen)
+                && grid.get_bloque(local.x as usize, local.y as usize, local.z as usize) != AIRE;
+            if !adentro {
+                origenes.push(origen);
+            }
+        }
+        origenes
+    }
+
+    #[test]
+    fn dda_coincide_con_los_cubos() {
+        let (grid, cubos) = escena();
+        let mut rng = Rng::new(23);
+
+        for origen in origenes(&grid, &mut rng, 2000) {
+            let direccion = Vec3::new(rng.next_f32() - 0.5, rng.next_f32() - 0.5, rng.next_f32() - 0.5).normalize();
+            comparar(&grid, &cubos, &origen, &direccion);
+        }
+    }
+
+    #[test]
+    fn dda_coincide_con_los_cubos_en_los_ejes() {
+        let (grid, cubos) = escena();
+        let mut rng = Rng::new(29);
+
+        for origen in origenes(&grid, &mut rng, 300) {
+            for eje in 0..3 {
+                for signo in [-1.0, 1.0] {
+                    let mut direccion = Vec3::zeros();
+                    direccion[eje] = signo;
+                    comparar(&grid, &cubos, &origen, &direccion);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dda_sin_direccion_no_golpea() {
+        let (grid, _) = escena();
+        let origen = Vec3::new(0.1, 0.2, -1.3);
+
+        assert!(!grid.ray_intersect(&origen, &Vec3::zeros()).is_intersecting);
+        assert!(!grid.ray_intersect(&origen, &Vec3::new(f32::NAN, 0.0, 1.0)).is_intersecting);
+        assert!(!grid.ray_intersect(&origen, &Vec3::new(f32::INFINITY, 0.0, 0.0)).is_intersecting);
+    }
+}